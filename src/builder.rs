@@ -0,0 +1,240 @@
+use crate::clock::Clock;
+use crate::command::CommandRecord;
+use crate::policy::LeafFloodPolicy;
+use crate::telemetry::TelemetryEvent;
+use crate::GetDroned;
+use crossbeam_channel::{Receiver, Sender};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::NodeId;
+use wg_2024::packet::Packet;
+
+/// Builder of a `GetDroned`, to configure a drone declaratively instead of calling
+/// its setters one by one after `GetDroned::new`.
+///
+/// Every option left unset keeps the default of `GetDroned::new`.
+#[derive(Debug)]
+pub struct GetDronedBuilder {
+    id: NodeId,
+    controller_send: Sender<DroneEvent>,
+    controller_recv: Receiver<DroneCommand>,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    pdr: f32,
+    clock: Option<Arc<dyn Clock>>,
+    send_block_tracking: bool,
+    nack_aggregation_window: Option<Duration>,
+    default_route: Option<NodeId>,
+    rng_seed: Option<u64>,
+    header_corruption_rate: f32,
+    telemetry_sender: Option<Sender<TelemetryEvent>>,
+    leaf_flood_policy: LeafFloodPolicy,
+    command_history_capacity: usize,
+    received_floods: HashSet<(NodeId, u64)>,
+    crashed_neighbors: HashSet<NodeId>,
+    startup_commands: Vec<CommandRecord>,
+    startup_senders: HashMap<NodeId, Sender<Packet>>,
+    bounded_channels: bool,
+}
+
+impl GetDronedBuilder {
+    /// Creates a builder with the parameters required by `GetDroned::new`.
+    pub fn new(
+        id: NodeId,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        pdr: f32,
+    ) -> Self {
+        GetDronedBuilder {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            pdr,
+            clock: None,
+            send_block_tracking: false,
+            nack_aggregation_window: None,
+            default_route: None,
+            rng_seed: None,
+            header_corruption_rate: 0.0,
+            telemetry_sender: None,
+            leaf_flood_policy: LeafFloodPolicy::default(),
+            command_history_capacity: 0,
+            received_floods: HashSet::new(),
+            crashed_neighbors: HashSet::new(),
+            startup_commands: Vec::new(),
+            startup_senders: HashMap::new(),
+            bounded_channels: false,
+        }
+    }
+
+    /// See `GetDroned::set_clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// See `GetDroned::set_send_block_tracking`.
+    pub fn with_send_block_tracking(mut self, enabled: bool) -> Self {
+        self.send_block_tracking = enabled;
+        self
+    }
+
+    /// See `GetDroned::set_nack_aggregation_window`.
+    pub fn with_nack_aggregation_window(mut self, window: Duration) -> Self {
+        self.nack_aggregation_window = Some(window);
+        self
+    }
+
+    /// See `GetDroned::set_default_route`.
+    pub fn with_default_route(mut self, gateway: NodeId) -> Self {
+        self.default_route = Some(gateway);
+        self
+    }
+
+    /// See `GetDroned::set_rng_seed`.
+    pub fn with_rng_seed(mut self, seed: u64) -> Self {
+        self.rng_seed = Some(seed);
+        self
+    }
+
+    /// See `GetDroned::set_header_corruption_rate`.
+    pub fn with_header_corruption_rate(mut self, rate: f32) -> Self {
+        self.header_corruption_rate = rate;
+        self
+    }
+
+    /// See `GetDroned::set_telemetry_sender`.
+    pub fn with_telemetry_sender(mut self, sender: Sender<TelemetryEvent>) -> Self {
+        self.telemetry_sender = Some(sender);
+        self
+    }
+
+    /// See `GetDroned::set_leaf_flood_policy`.
+    pub fn with_leaf_flood_policy(mut self, policy: LeafFloodPolicy) -> Self {
+        self.leaf_flood_policy = policy;
+        self
+    }
+
+    /// See `GetDroned::set_command_history_capacity`.
+    pub fn with_command_history_capacity(mut self, capacity: usize) -> Self {
+        self.command_history_capacity = capacity;
+        self
+    }
+
+    /// Pre-seeds the floods the drone considers already received, as `(initiator_id, flood_id)`.
+    /// The drone answers these floods directly instead of forwarding them.
+    pub fn with_received_floods(mut self, floods: impl IntoIterator<Item = (NodeId, u64)>) -> Self {
+        self.received_floods.extend(floods);
+        self
+    }
+
+    /// Pre-seeds the neighbors the drone considers known crashed, as reported by
+    /// `GetDroned::neighbor_status`, until a send to them succeeds.
+    pub fn with_crashed_neighbors(mut self, neighbors: impl IntoIterator<Item = NodeId>) -> Self {
+        self.crashed_neighbors.extend(neighbors);
+        self
+    }
+
+    /// See `GetDroned::set_bounded_channels`.
+    pub fn with_bounded_channels(mut self, bounded: bool) -> Self {
+        self.bounded_channels = bounded;
+        self
+    }
+
+    /// Commands applied to the drone when it is built, after every other option.
+    /// See `GetDroned::replay_commands`.
+    pub fn with_startup_commands(
+        mut self,
+        commands: Vec<CommandRecord>,
+        senders: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        self.startup_commands = commands;
+        self.startup_senders = senders;
+        self
+    }
+
+    /// Builds the drone.
+    pub fn build(self) -> GetDroned {
+        let mut drone = GetDroned::new(
+            self.id,
+            self.controller_send,
+            self.controller_recv,
+            self.packet_recv,
+            self.packet_send,
+            self.pdr,
+        );
+        if let Some(clock) = self.clock {
+            drone.set_clock(clock);
+        }
+        drone.set_send_block_tracking(self.send_block_tracking);
+        drone.set_nack_aggregation_window(self.nack_aggregation_window);
+        drone.set_default_route(self.default_route);
+        if let Some(seed) = self.rng_seed {
+            drone.set_rng_seed(seed);
+        }
+        drone.set_header_corruption_rate(self.header_corruption_rate);
+        drone.set_telemetry_sender(self.telemetry_sender);
+        drone.set_leaf_flood_policy(self.leaf_flood_policy);
+        drone.set_command_history_capacity(self.command_history_capacity);
+        drone.set_bounded_channels(self.bounded_channels);
+        drone.seed_received_floods(self.received_floods);
+        drone.seed_crashed_neighbors(self.crashed_neighbors);
+        drone.replay_commands(&self.startup_commands, &self.startup_senders);
+        drone
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighbor::NeighborStatus;
+    use crossbeam_channel::unbounded;
+
+    /// Creates a builder of drone 1 with neighbors 2 and 3, and no drop.
+    fn builder() -> GetDronedBuilder {
+        let (controller_send, _) = unbounded();
+        let (_, controller_recv) = unbounded();
+        let (_, packet_recv) = unbounded();
+        let packet_send = [2, 3].into_iter().map(|id| (id, unbounded().0)).collect();
+        GetDronedBuilder::new(
+            1,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            0.0,
+        )
+    }
+
+    #[test]
+    fn crashed_neighbors_are_seeded() {
+        let drone = builder().with_crashed_neighbors([3, 9]).build();
+        assert_eq!(drone.neighbor_status(2), NeighborStatus::Alive);
+        assert_eq!(drone.neighbor_status(3), NeighborStatus::KnownCrashed);
+        assert_eq!(drone.neighbor_status(9), NeighborStatus::Unknown);
+    }
+
+    #[test]
+    fn received_floods_are_seeded() {
+        let drone = builder()
+            .with_received_floods([(5, 2), (4, 1)])
+            .with_received_floods([(5, 3)])
+            .build();
+        assert_eq!(drone.export_received_floods(), vec![(4, 1), (5, 2), (5, 3)]);
+    }
+
+    #[test]
+    fn unset_options_keep_the_defaults() {
+        let drone = builder().build();
+        assert_eq!(drone.neighbor_status(3), NeighborStatus::Alive);
+        assert!(drone.export_received_floods().is_empty());
+        assert!(drone.command_history().is_empty());
+    }
+}
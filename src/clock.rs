@@ -0,0 +1,58 @@
+use std::fmt::Debug;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Source of time used by the drone for every time-based measurement.
+///
+/// The drone never calls `Instant::now()` directly: it asks its clock instead, so that tests and
+/// simulations can replace the wall clock with a [`ManualClock`] and control time explicitly.
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current instant according to this clock.
+    fn now(&self) -> Instant;
+}
+
+/// Clock backed by the system monotonic clock. This is the default clock of every drone.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when it is explicitly advanced.
+///
+/// Share it with the drone through an `Arc` and call [`ManualClock::advance`] to make time pass.
+#[derive(Debug)]
+pub struct ManualClock {
+    origin: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a manual clock frozen at the current instant.
+    pub fn new() -> Self {
+        ManualClock {
+            origin: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.origin + *self.elapsed.lock().unwrap()
+    }
+}
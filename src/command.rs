@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use wg_2024::controller::DroneCommand;
+use wg_2024::network::NodeId;
+
+/// A `DroneCommand` stripped of its channels, so that it can be stored and serialized.
+///
+/// Storing the `DroneCommand` itself would keep a clone of the `AddSender` channel alive,
+/// preventing the neighbor from ever seeing its receiver disconnect.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CommandRecord {
+    /// `DroneCommand::AddSender`, with the ID of the added neighbor.
+    AddSender(NodeId),
+    /// `DroneCommand::RemoveSender`, with the ID of the removed neighbor.
+    RemoveSender(NodeId),
+    /// `DroneCommand::SetPacketDropRate`, with the new packet drop rate.
+    SetPacketDropRate(f32),
+    /// `DroneCommand::Crash`.
+    Crash,
+}
+
+impl From<&DroneCommand> for CommandRecord {
+    fn from(command: &DroneCommand) -> Self {
+        match command {
+            DroneCommand::AddSender(id, _) => CommandRecord::AddSender(*id),
+            DroneCommand::RemoveSender(id) => CommandRecord::RemoveSender(*id),
+            DroneCommand::SetPacketDropRate(pdr) => CommandRecord::SetPacketDropRate(*pdr),
+            DroneCommand::Crash => CommandRecord::Crash,
+        }
+    }
+}
+
+/// Content of a command file, listing the commands to replay in order, e.g.:
+///
+/// ```toml
+/// commands = [{ SetPacketDropRate = 0.1 }, { AddSender = 3 }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandFile {
+    /// The commands, in the order they are applied.
+    pub commands: Vec<CommandRecord>,
+}
+
+/// Loads the commands of a TOML command file (see `CommandFile`), to replay them on a drone
+/// with `GetDroned::replay_commands` or `GetDronedBuilder::with_startup_commands`.
+///
+/// # Parameters
+/// - `path`: The path of the command file.
+///
+/// # Returns
+/// - `Ok(Vec<CommandRecord>)` with the commands, in order.
+/// - `Err` if the file cannot be read or is not a valid command file.
+pub fn load_command_file(path: impl AsRef<Path>) -> Result<Vec<CommandRecord>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let file: CommandFile = toml::from_str(&content)?;
+    Ok(file.commands)
+}
+
+/// Commands specific to GetDroned, sent on the optional extension command channel
+/// (see `GetDroned::set_extension_command_receiver`), since `DroneCommand` cannot be extended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GetDronedCommand {
+    /// Reseeds the random number generator of the drone, starting a reproducible phase.
+    ReseedRng(u64),
+    /// Reports the current stats with a `TelemetryEvent::StatsReset`, then resets them.
+    ResetStats,
+    /// Logs the full configuration and state of the drone at info level, in a single entry.
+    DumpConfig,
+    /// Holds the fragments received by the drone (`true`), or releases them (`false`).
+    /// See `GetDroned::set_held`.
+    Hold(bool),
+}
@@ -0,0 +1,160 @@
+use crate::get_droned::DEFAULT_SESSION_IDLE_TIMEOUT;
+use crate::health::HealthThresholds;
+use crate::policy::{
+    DropModel, FragmentOrder, HeldFloodPolicy, InvalidControlPolicy, LeafFloodPolicy,
+    LinkDropRates, PendingFlushPolicy, ReservedSessionPolicy,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use wg_2024::network::NodeId;
+
+/// Snapshot of all the settings of a drone, returned by `GetDroned::config`.
+///
+/// It describes how the drone is configured, not what it did (see `DroneStats` for that),
+/// and can be compared with another snapshot to spot configuration changes.
+/// It holds no channel, so a controller can serialize it (for example to TOML) to persist the
+/// view of a drone and restore it later; malformed input is rejected with an error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroneConfigSnapshot {
+    /// Unique identifier of the drone.
+    pub id: NodeId,
+    /// IDs of the neighbors, sorted.
+    pub neighbors: Vec<NodeId>,
+    /// Probability of dropping a fragment.
+    pub packet_drop_rate: f32,
+    /// Probability of corrupting the routing header of a forwarded packet.
+    pub header_corruption_rate: f32,
+    /// Gateway used when the next hop is not a neighbor.
+    pub default_route: Option<NodeId>,
+    /// Policy deciding when the drone answers a flood request as a leaf.
+    pub leaf_flood_policy: LeafFloodPolicy,
+    /// Window of the NACK aggregation.
+    pub nack_aggregation_window: Option<Duration>,
+    /// Maximum number of active sessions.
+    pub max_active_sessions: Option<usize>,
+    /// Time after which an idle session is no longer active.
+    pub session_idle_timeout: Duration,
+    /// Maximum payload size of a forwarded fragment, in bytes.
+    pub max_fragment_bytes: Option<usize>,
+    /// Size of the command history.
+    pub command_history_capacity: usize,
+    /// Whether the time spent blocked on send is measured.
+    pub send_block_tracking: bool,
+    /// Interval of the periodic stats summary.
+    pub stats_summary_interval: Option<Duration>,
+    /// Policy deciding what happens to the pending packets when they are flushed.
+    pub pending_flush_policy: PendingFlushPolicy,
+    /// Maximum random delay before the first periodic tick.
+    pub startup_jitter: Option<Duration>,
+    /// Policy deciding what a held drone does with the flood requests.
+    pub held_flood_policy: HeldFloodPolicy,
+    /// Size of the outcome history.
+    pub outcome_history_capacity: usize,
+    /// Whether inconsistent flood requests are discarded.
+    pub strict_flood_validation: bool,
+    /// Maximum number of hops of an accepted routing header.
+    pub max_route_hops: Option<usize>,
+    /// Maximum size of a batch of commands applied in a random order.
+    pub command_reorder_batch: Option<usize>,
+    /// Number of events sent to the controller at once.
+    pub event_batch_size: usize,
+    /// Maximum number of NACKs sent per second.
+    pub nack_rate_limit: Option<u32>,
+    /// Time during which a removed neighbor is listed as recently removed.
+    pub removed_neighbor_retention: Duration,
+    /// Neighbor receiving a copy of every forwarded fragment.
+    pub tap: Option<NodeId>,
+    /// Thresholds above which the drone reports itself unhealthy.
+    pub health_thresholds: HealthThresholds,
+    /// Maximum random delay before forwarding a flood request.
+    pub flood_jitter: Option<Duration>,
+    /// Whether packets with a mis-addressed first hop are redirected.
+    pub first_hop_correction: bool,
+    /// Number of forwarding workers.
+    pub forward_workers: usize,
+    /// Whether the neighbor channels are bounded.
+    pub bounded_channels: bool,
+    /// Whether duplicate flood responses are suppressed.
+    pub flood_response_dedup: bool,
+    /// Whether the path traces of flood requests are checked for regressions.
+    pub flood_trace_check: bool,
+    /// Size of the event log.
+    pub event_log_capacity: usize,
+    /// Model deciding which fragments are dropped.
+    pub drop_model: DropModel,
+    /// Whether every send to a neighbor is reported with attempt and result telemetry events.
+    pub send_attempt_events: bool,
+    /// Whether the distinct flood responses are aggregated next to their initiator.
+    pub initiator_response_dedup: bool,
+    /// Directional drop rates of the links to the neighbors that have some, sorted by neighbor.
+    pub link_drop_rates: Vec<(NodeId, LinkDropRates)>,
+    /// Minimum intervals between two packets sent to the neighbors that have one, sorted by neighbor.
+    pub neighbor_min_gaps: Vec<(NodeId, Duration)>,
+    /// Delay before the single retry of a fragment drawn as dropped.
+    pub drop_retry_delay: Option<Duration>,
+    /// Policy deciding what happens to the control packets with an invalid routing header.
+    pub invalid_control_policy: InvalidControlPolicy,
+    /// Observed drop ratio above which a warning is logged, with its observation window.
+    pub drop_rate_warning: Option<(f64, Duration)>,
+    /// Whether flood requests skip every neighbor already in their path trace.
+    pub skip_traced_flood_neighbors: bool,
+    /// Maximum number of fragments buffered while the drone is held.
+    pub hold_capacity: usize,
+    /// Order in which the fragments of a session are forwarded.
+    pub fragment_order: FragmentOrder,
+    /// Policy deciding what happens to the packets of the reserved session 0.
+    pub reserved_session_policy: ReservedSessionPolicy,
+}
+
+/// Full configuration profile of a drone, applied at once with `GetDroned::apply_profile`.
+///
+/// The default profile matches the defaults of `GetDroned::new` (with a packet drop rate of 0).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroneProfile {
+    /// Probability of dropping a fragment.
+    pub packet_drop_rate: f32,
+    /// Probability of corrupting the routing header of a forwarded packet.
+    pub header_corruption_rate: f32,
+    /// Gateway used when the next hop is not a neighbor.
+    pub default_route: Option<NodeId>,
+    /// Policy deciding when the drone answers a flood request as a leaf.
+    pub leaf_flood_policy: LeafFloodPolicy,
+    /// Window of the NACK aggregation.
+    pub nack_aggregation_window: Option<Duration>,
+    /// Maximum number of active sessions.
+    pub max_active_sessions: Option<usize>,
+    /// Time after which an idle session is no longer active.
+    pub session_idle_timeout: Duration,
+    /// Policy deciding what happens to the pending packets when they are flushed.
+    pub pending_flush_policy: PendingFlushPolicy,
+    /// Policy deciding what a held drone does with the flood requests.
+    pub held_flood_policy: HeldFloodPolicy,
+    /// Model deciding which fragments are dropped.
+    pub drop_model: DropModel,
+    /// Directional drop rates of the links to the neighbors that have some.
+    pub link_drop_rates: Vec<(NodeId, LinkDropRates)>,
+    /// Policy deciding what happens to the control packets with an invalid routing header.
+    pub invalid_control_policy: InvalidControlPolicy,
+    /// Policy deciding what happens to the packets of the reserved session 0.
+    pub reserved_session_policy: ReservedSessionPolicy,
+}
+
+impl Default for DroneProfile {
+    fn default() -> Self {
+        DroneProfile {
+            packet_drop_rate: 0.0,
+            header_corruption_rate: 0.0,
+            default_route: None,
+            leaf_flood_policy: LeafFloodPolicy::default(),
+            nack_aggregation_window: None,
+            max_active_sessions: None,
+            session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+            pending_flush_policy: PendingFlushPolicy::default(),
+            held_flood_policy: HeldFloodPolicy::default(),
+            drop_model: DropModel::default(),
+            link_drop_rates: Vec::new(),
+            invalid_control_policy: InvalidControlPolicy::default(),
+            reserved_session_policy: ReservedSessionPolicy::default(),
+        }
+    }
+}
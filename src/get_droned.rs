@@ -1,44 +1,342 @@
-use crossbeam_channel::{select_biased, Receiver, Sender};
-use flexi_logger::{Age, Cleanup, Criterion::Age as AgeCriterion, FileSpec, Logger, Naming};
-use log::{info, warn};
-use std::collections::{HashMap, HashSet};
+use crate::clock::{Clock, SystemClock};
+use crate::command::{CommandRecord, GetDronedCommand};
+use crate::config::{DroneConfigSnapshot, DroneProfile};
+use crate::health::{DroneHealth, HealthStatus, HealthThresholds};
+use crate::neighbor::NeighborStatus;
+use crate::outcome::ForwardOutcome;
+use crate::policy::{
+    DropModel, FragmentOrder, HeldFloodPolicy, InvalidControlPolicy, LeafFloodPolicy,
+    LinkDropRates, PendingFlushPolicy, ReservedSessionPolicy,
+};
+#[cfg(any(test, feature = "test-utils"))]
+use crate::recording::TimelineEntry;
+use crate::stats::{DroneStats, RoleSummary, RouteLengthHistogram};
+use crate::telemetry::{ShortcutReason, TelemetryEvent};
+use crate::trace::PathTraceMap;
+use crossbeam_channel::{
+    after, never, select_biased, tick, unbounded, Receiver, SendError, Sender, TrySendError,
+};
+use flexi_logger::writers::{FileLogWriter, LogWriter};
+use flexi_logger::{
+    Age, Cleanup, Criterion::Age as AgeCriterion, DeferredNow, FileSpec, FlexiLoggerError, Logger,
+    Naming,
+};
+use log::{debug, info, warn, Record};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::cell::Cell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fmt::Display;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use wg_2024::controller::{DroneCommand, DroneEvent};
 use wg_2024::drone::Drone;
-use wg_2024::network::NodeId;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
 use wg_2024::packet::{FloodRequest, Nack, NackType, NodeType, Packet, PacketType};
 
+/// Log target of the flood propagation decisions, logged at debug level.
+/// They can be enabled selectively with a log specification like `info, drone::flood=debug`.
+pub const FLOOD_LOG_TARGET: &str = "drone::flood";
+
+/// Default time after which a session without forwarded fragments is no longer active.
+pub(crate) const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time during which a removed neighbor is kept in the recently removed neighbors.
+pub(crate) const DEFAULT_REMOVED_NEIGHBOR_RETENTION: Duration = Duration::from_secs(30);
+
+/// Default maximum number of hops of a routing header: with 8-bit node IDs, a route without
+/// loops cannot be longer.
+pub(crate) const DEFAULT_MAX_ROUTE_HOPS: usize = 256;
+
+/// Number of attempts to send on a full bounded neighbor channel before giving up.
+const BOUNDED_SEND_ATTEMPTS: u32 = 10;
+
+/// Pause between two attempts to send on a full bounded neighbor channel.
+const BOUNDED_SEND_RETRY_DELAY: Duration = Duration::from_millis(1);
+
+/// Maximum number of sessions whose fragment losses are tracked for `session_loss`.
+const MAX_TRACKED_LOSS_SESSIONS: usize = 1024;
+
+/// Maximum number of floods whose fanout is tracked for `flood_fanout`.
+const MAX_TRACKED_FLOOD_FANOUTS: usize = 1024;
+
+/// Maximum number of forwarded flood responses remembered for their deduplication.
+const MAX_TRACKED_FLOOD_RESPONSES: usize = 4096;
+
+/// Maximum number of `(flood, sender)` pairs whose path trace length is remembered for the
+/// path trace check.
+const MAX_TRACKED_FLOOD_TRACES: usize = 4096;
+
+/// Maximum number of floods whose responses are aggregated next to their initiator.
+const MAX_TRACKED_INITIATOR_FLOODS: usize = 256;
+
+/// Number of recent forwarded fragments summarized by `role_summary`.
+const ROLE_WINDOW: usize = 256;
+
+/// Minimum number of fragments in a window before its drop ratio is compared with the warning threshold.
+const DROP_RATE_WARNING_MIN_SAMPLES: u64 = 10;
+
+/// Default maximum number of fragments buffered while the drone is held.
+const DEFAULT_HOLD_CAPACITY: usize = 256;
+
+/// Period of the central tick driving the periodic work of the drone, when any is enabled.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Fragment handed to a forwarding worker: target neighbor, its channel, the packet to send,
+/// and the packet as received, to NACK if the send fails.
+type ForwardJob = (NodeId, Sender<Packet>, Packet, Packet);
+
+/// Result of a forwarding job reported to the drone loop: target neighbor, and the packet sent,
+/// or the packet as received if the send failed.
+type ForwardResult = (NodeId, Result<Packet, Packet>);
+
+/// Callback invoked when a drone generates a flood response, with the flood ID and the route
+/// of the generated response.
+pub type FloodResponseHook = dyn FnMut(u64, &SourceRoutingHeader) + Send;
+
+/// Callback invoked when a drone loses its last neighbor, with the ID of the drone.
+pub type IsolationHook = dyn FnMut(NodeId) + Send;
+
+/// Callback invoked when a `SetPacketDropRate` command changes the packet drop rate,
+/// with the old and the new rate.
+pub type PdrChangeHook = dyn FnMut(f32, f32) + Send;
+
+/// Resolver consulted when the next hop of a packet is not a neighbor, with the destination of
+/// the packet (the last hop of its route), returning the neighbor to forward it to, if any.
+pub type NextHopResolver = dyn Fn(NodeId) -> Option<NodeId> + Send;
+
+/// Callback installed by the embedder, shown as opaque in the `Debug` output of the drone.
+struct Hook<F: ?Sized>(Box<F>);
+
+impl<F: ?Sized> std::fmt::Debug for Hook<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Hook")
+    }
+}
+
+/// Simulation run ID included in every log line, set by `init_logger_with_run_id`.
+static RUN_ID: OnceLock<String> = OnceLock::new();
+
+/// Whether the global logger is already initialized, by GetDroned or by the embedder.
+static LOGGER_STARTED: Mutex<bool> = Mutex::new(false);
+
+/// Directory of the log files written by the GetDroned loggers.
+const LOG_DIRECTORY: &str = "getdroned_logs";
+
+thread_local! {
+    /// ID of the drone running on the current thread, set by `run`, used to route the log
+    /// lines of each drone to its own files (see `init_logger_for_drone`).
+    static CURRENT_DRONE: Cell<Option<NodeId>> = const { Cell::new(None) };
+}
+
 /// Initialize a global logger for the GetDroned drone.
 /// You can initialize the logger in your network initializer or main function using this function.
 /// but you can create your own logger in your code and use all the log of the GetDroned drone.
+/// It can be called once per drone: if a global logger is already initialized, it does nothing.
 pub fn init_logger() -> Result<(), Box<dyn Error>> {
-    Logger::try_with_str("info")
-        .unwrap()
-        .log_to_file(
-            FileSpec::default()
-                .directory("getdroned_logs")
-                .basename("")
-                .suffix("log"),
-        )
-        .rotate(
-            AgeCriterion(Age::Day),
-            Naming::Timestamps,
-            Cleanup::KeepLogFiles(10),
-        )
-        .format(|writer, now, record| {
-            write!(
-                writer,
-                "{} [{}] [{}] {}",
-                now.format("%Y-%m-%d %H:%M:%S"),
-                record.level(),
-                record.target(),
-                record.args()
+    init_logger_with_level("info")
+}
+
+/// Initialize the global logger like `init_logger`, with the given log specification instead of
+/// `info`, e.g. `debug` to investigate a drone, `warn` to quiet a large simulation, or
+/// `info, drone::flood=debug` to enable a single target.
+///
+/// # Parameters
+/// - `level`: The log specification, in the `flexi_logger` syntax.
+///
+/// # Returns
+/// An error if the specification cannot be parsed or the logger cannot be started.
+pub fn init_logger_with_level(level: &str) -> Result<(), Box<dyn Error>> {
+    start_logger(level, false)
+}
+
+/// Initialize the global logger like `init_logger`, with separate log files for each drone, named
+/// after its ID (e.g. `drone_3_2024-11-20_10-00-00.log`), so that drones do not write or rotate
+/// into the same files, whether they run in the same process or in different processes sharing
+/// the log directory. The lines logged by a drone thread go to the files of its drone, and the
+/// other lines to the shared files named like those of `init_logger`.
+///
+/// # Parameters
+/// - `id`: The ID of the drone running on the calling thread, if any. Drones started with `run`
+///   identify their own thread.
+pub fn init_logger_for_drone(id: NodeId) -> Result<(), Box<dyn Error>> {
+    CURRENT_DRONE.with(|drone| drone.set(Some(id)));
+    start_logger("info", true)
+}
+
+/// Starts the global logger, writing to rotated files in the `getdroned_logs` directory.
+/// Succeeds without doing anything if a global logger is already initialized, whether it was
+/// started by a previous call or installed by the embedder.
+///
+/// # Parameters
+/// - `level`: The log specification, checked even if the logger is already initialized.
+/// - `per_drone`: Whether each drone writes to its own files (see `DroneLogWriter`).
+fn start_logger(level: &str, per_drone: bool) -> Result<(), Box<dyn Error>> {
+    let logger = Logger::try_with_str(level)?;
+    let mut started = LOGGER_STARTED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *started {
+        return Ok(());
+    }
+    let logger = if per_drone {
+        logger.log_to_writer(Box::new(DroneLogWriter::new(LOG_DIRECTORY)))
+    } else {
+        logger
+            .log_to_file(log_file_spec(LOG_DIRECTORY, ""))
+            .rotate(
+                AgeCriterion(Age::Day),
+                Naming::Timestamps,
+                Cleanup::KeepLogFiles(10),
+            )
+            .format(format_log_line)
+    };
+    let result = logger.start();
+    match result {
+        // Another logger was installed by the embedder: the logs of the drone go there.
+        Ok(_) | Err(FlexiLoggerError::Log(_)) => {
+            *started = true;
+            Ok(())
+        }
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+/// Returns the specification of the log files with the given name prefix.
+///
+/// # Parameters
+/// - `directory`: The directory of the log files.
+/// - `basename`: The prefix of the log file names, before the timestamp.
+fn log_file_spec(directory: impl Into<PathBuf>, basename: &str) -> FileSpec {
+    FileSpec::default()
+        .directory(directory)
+        .basename(basename)
+        .suffix("log")
+}
+
+/// Formats a log line, with the simulation run ID if one is set.
+fn format_log_line(
+    writer: &mut dyn Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> io::Result<()> {
+    match RUN_ID.get() {
+        Some(run_id) => write!(
+            writer,
+            "{} [{}] [{}] [{}] {}",
+            now.format("%Y-%m-%d %H:%M:%S"),
+            run_id,
+            record.level(),
+            record.target(),
+            record.args()
+        ),
+        None => write!(
+            writer,
+            "{} [{}] [{}] {}",
+            now.format("%Y-%m-%d %H:%M:%S"),
+            record.level(),
+            record.target(),
+            record.args()
+        ),
+    }
+}
+
+/// Log writer giving each drone its own rotated log files, named after its ID. A line is routed
+/// to the files of the drone running on the logging thread, or to the shared files if none is.
+/// The files of a drone are created with its first line.
+pub(crate) struct DroneLogWriter {
+    /// Directory of the log files.
+    directory: PathBuf,
+    /// File writer of each drone, and of the lines logged outside drone threads (`None`).
+    writers: Mutex<HashMap<Option<NodeId>, FileLogWriter>>,
+}
+
+impl DroneLogWriter {
+    /// Creates a writer with no files yet.
+    ///
+    /// # Parameters
+    /// - `directory`: The directory of the log files.
+    pub(crate) fn new(directory: impl Into<PathBuf>) -> Self {
+        DroneLogWriter {
+            directory: directory.into(),
+            writers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates the file writer of a drone, or of the lines logged outside drone threads.
+    fn file_writer(&self, drone: Option<NodeId>) -> io::Result<FileLogWriter> {
+        let basename = drone.map_or_else(String::new, |id| format!("drone_{}", id));
+        FileLogWriter::builder(log_file_spec(self.directory.clone(), &basename))
+            .rotate(
+                AgeCriterion(Age::Day),
+                Naming::Timestamps,
+                Cleanup::KeepLogFiles(10),
             )
-        })
-        .start()
-        .map(|_| ())
-        .map_err(|e| Box::new(e) as Box<dyn Error>)
+            .format(format_log_line)
+            .try_build()
+            .map_err(io::Error::other)
+    }
+}
+
+impl LogWriter for DroneLogWriter {
+    fn write(&self, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+        let drone = CURRENT_DRONE.with(Cell::get);
+        let mut writers = self
+            .writers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let writer = match writers.entry(drone) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.file_writer(drone)?),
+        };
+        writer.write(now, record)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let writers = self
+            .writers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for writer in writers.values() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn shutdown(&self) {
+        let writers = self
+            .writers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for writer in writers.values() {
+            writer.shutdown();
+        }
+    }
+}
+
+/// Initialize the global logger like `init_logger`, tagging every log line with a simulation run ID,
+/// so that the logs of different runs sharing a directory or an aggregator can be told apart.
+/// The run ID can only be set once per process: later calls keep the first one.
+pub fn init_logger_with_run_id(run_id: &str) -> Result<(), Box<dyn Error>> {
+    let _ = RUN_ID.set(run_id.to_string());
+    init_logger()
+}
+
+/// Hashes a list of values into a well-mixed 64-bit value (SplitMix64 finalizer), stable across
+/// runs and platforms, unlike the standard hashers.
+fn stable_hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9E37_79B9_7F4A_7C15, |hash, value| {
+        let mut z = (hash ^ value).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    })
 }
 
 /// Represents a drone in the simulation.
@@ -64,6 +362,210 @@ pub struct GetDroned {
     is_crashed: bool,
 
     received_floods: HashSet<(NodeId, u64)>,
+
+    /// Clock used for every time measurement of the drone.
+    clock: Arc<dyn Clock>,
+    /// Whether the time spent blocked on neighbor channels is measured (off by default).
+    track_send_block_time: bool,
+    /// Total time spent blocked while sending packets to neighbors.
+    send_block_time: Duration,
+    /// Window during which NACKs of a session on the same return route are coalesced (off if `None`).
+    nack_aggregation_window: Option<Duration>,
+    /// Time of the last NACK sent for each (session, return route), used for NACK aggregation.
+    recent_nacks: HashMap<(u64, Vec<NodeId>), Instant>,
+    /// Neighbor used as gateway when the next hop of a packet is not a neighbor (off if `None`).
+    default_route: Option<NodeId>,
+    /// Random number generator used for every random decision of the drone.
+    rng: StdRng,
+    /// Probability of flipping a bit of the routing header of a forwarded packet (0.00 to 1.00).
+    header_corruption_rate: f32,
+    /// Optional channel where GetDroned-specific events are sent.
+    telemetry_sender: Option<Sender<TelemetryEvent>>,
+    /// Policy deciding when the drone answers a flood request as a leaf of the network.
+    leaf_flood_policy: LeafFloodPolicy,
+    /// Maximum number of commands kept in the command history (0 disables the history).
+    command_history_capacity: usize,
+    /// The last commands received, with the time they were received at, oldest first.
+    command_history: VecDeque<(Instant, CommandRecord)>,
+    /// Activity counters of the drone.
+    stats: DroneStats,
+    /// Callback invoked when the drone generates a flood response.
+    on_flood_response: Option<Hook<FloodResponseHook>>,
+    /// Maximum number of sessions the drone forwards fragments for at the same time (unlimited if `None`).
+    max_active_sessions: Option<usize>,
+    /// Time after which a session without forwarded fragments is no longer active.
+    session_idle_timeout: Duration,
+    /// Active sessions, with the time their last fragment was accepted. Only tracked with a session cap.
+    active_sessions: HashMap<u64, Instant>,
+    /// Maximum payload size of a forwarded fragment, in bytes (unlimited if `None`).
+    max_fragment_bytes: Option<usize>,
+    /// Neighbors the drone successfully sent packets to.
+    sent_to: HashSet<NodeId>,
+    /// Nodes the drone received packets from, inferred from the incoming packets.
+    received_from: HashSet<NodeId>,
+    /// Interval between two stats summary lines in the log (off if `None`).
+    stats_summary_interval: Option<Duration>,
+    /// Time of the last stats summary, `None` until the first tick.
+    last_stats_summary: Option<Instant>,
+    /// Stats at the time of the last summary, to log the activity since then.
+    last_summary_stats: DroneStats,
+    /// Policy deciding what happens to the pending packets when they are flushed.
+    pending_flush_policy: PendingFlushPolicy,
+    /// Per-neighbor receivers used in strict mode, until they are moved to their forwarding threads by `run`.
+    neighbor_receivers: HashMap<NodeId, Receiver<Packet>>,
+    /// Callback invoked when the drone loses its last neighbor.
+    on_isolated: Option<Hook<IsolationHook>>,
+    /// Receiver of the GetDroned-specific commands (never receives anything if not set).
+    extension_command_receiver: Receiver<GetDronedCommand>,
+    /// Maximum random delay before the first tick of the periodic work (no delay if `None`).
+    startup_jitter: Option<Duration>,
+    /// Policy deciding what a held drone does with the flood requests.
+    held_flood_policy: HeldFloodPolicy,
+    /// Maximum number of outcomes kept in `recent_outcomes` (0 disables the history).
+    outcome_history_capacity: usize,
+    /// Outcomes of the last processed packets, oldest first.
+    recent_outcomes: VecDeque<ForwardOutcome>,
+    /// Neighbors whose channel was found disconnected by the last send to them.
+    crashed_neighbors: HashSet<NodeId>,
+    /// Shared map where the drone records the fragments it receives (no tracing if `None`).
+    path_traces: Option<PathTraceMap>,
+    /// Whether the logger started by `run` includes the drone ID in the log file names.
+    log_file_per_drone: bool,
+    /// Whether flood requests whose path trace does not start with their initiator are discarded
+    /// instead of only logged.
+    strict_flood_validation: bool,
+    /// Side effects recorded since `start_recording` (not recording if `None`).
+    #[cfg(any(test, feature = "test-utils"))]
+    timeline: Option<Vec<TimelineEntry>>,
+    /// Maximum number of hops of an accepted routing header (no limit if `None`).
+    max_route_hops: Option<usize>,
+    /// Maximum number of waiting commands applied together in a random order (strict order if `None`).
+    command_reorder_batch: Option<usize>,
+    /// Channel where the events are sent in batches instead of `event_sender` (unbatched if `None`).
+    event_batch_sender: Option<Sender<Vec<DroneEvent>>>,
+    /// Number of pending events that triggers a batch.
+    event_batch_size: usize,
+    /// Events waiting to be sent in the next batch.
+    pending_events: Vec<DroneEvent>,
+    /// Known types of the neighbors, used to report deliveries to endpoints.
+    neighbor_types: HashMap<NodeId, NodeType>,
+    /// Maximum number of NACKs sent per second (unlimited if `None`).
+    nack_rate_limit: Option<u32>,
+    /// Tokens currently available in the NACK bucket.
+    nack_tokens: f64,
+    /// Time the NACK bucket was last refilled.
+    nack_tokens_refilled_at: Option<Instant>,
+    /// Neighbors removed within the retention window, with the time they were removed at.
+    recently_removed: HashMap<NodeId, Instant>,
+    /// Time during which a removed neighbor is kept in `recently_removed`.
+    removed_neighbor_retention: Duration,
+    /// Neighbor receiving a copy of every forwarded fragment (no copy if `None`).
+    tap: Option<NodeId>,
+    /// Thresholds used by `health`.
+    health_thresholds: HealthThresholds,
+    /// Time the drone was created or last received a packet, used for the idle time.
+    last_packet_at: Instant,
+    /// Whether the command channel was found disconnected by the drone loop.
+    command_channel_disconnected: bool,
+    /// Whether the packet channel was found disconnected by the drone loop while not crashed.
+    packet_channel_disconnected: bool,
+    /// Maximum random delay before forwarding a flood request (immediate if `None`).
+    flood_jitter: Option<Duration>,
+    /// Flood requests waiting for their jitter delay: due time, packet and node received from.
+    pending_floods: Vec<(Instant, Packet, NodeId)>,
+    /// Whether packets with a mis-addressed first hop are redirected instead of NACKed.
+    first_hop_correction: bool,
+    /// Histogram of the route lengths of the processed packets.
+    route_lengths: RouteLengthHistogram,
+    /// Number of forwarding workers started by `run` (fragments are sent by the loop if 0).
+    forward_worker_count: usize,
+    /// Job channels of the running forwarding workers.
+    forward_workers: Vec<Sender<ForwardJob>>,
+    /// Threads of the running forwarding workers, joined when the drone terminates.
+    forward_worker_threads: Vec<JoinHandle<()>>,
+    /// Channel of the results of the forwarding jobs (never delivers if the workers are disabled).
+    forward_results: Receiver<ForwardResult>,
+    /// Callback invoked when the packet drop rate is changed by a command.
+    on_pdr_change: Option<Hook<PdrChangeHook>>,
+    /// Whether the neighbor channels are bounded, switching the sends to `try_send` with retries.
+    bounded_channels: bool,
+    /// Number of fragments of each tracked session the drone did not forward.
+    session_losses: HashMap<u64, u32>,
+    /// Tracked sessions of `session_losses`, oldest first.
+    loss_sessions_order: VecDeque<u64>,
+    /// Whether flood responses already forwarded are suppressed.
+    flood_response_dedup: bool,
+    /// Flood responses forwarded, as `(initiator_id, flood_id, responder)`, for the deduplication.
+    forwarded_flood_responses: HashSet<(NodeId, u64, NodeId)>,
+    /// Tracked responses of `forwarded_flood_responses`, oldest first.
+    forwarded_responses_order: VecDeque<(NodeId, u64, NodeId)>,
+    /// Whether the path traces of flood requests are checked for regressions.
+    flood_trace_check: bool,
+    /// Longest path trace received for each `(initiator_id, flood_id, sender)`, for the path trace check.
+    flood_trace_lengths: HashMap<(NodeId, u64, NodeId), usize>,
+    /// Tracked keys of `flood_trace_lengths`, oldest first.
+    flood_traces_order: VecDeque<(NodeId, u64, NodeId)>,
+    /// Maximum number of events kept in `event_log` (0 disables the log).
+    event_log_capacity: usize,
+    /// Last events emitted by the drone, oldest first.
+    event_log: VecDeque<DroneEvent>,
+    /// Model deciding which fragments are dropped.
+    drop_model: DropModel,
+    /// Position of the next fragment in the cycle of `DropModel::BurstCycle`.
+    burst_position: u32,
+    /// Number of neighbors each tracked flood, as `(initiator_id, flood_id)`, was forwarded to.
+    flood_fanouts: HashMap<(NodeId, u64), usize>,
+    /// Tracked floods of `flood_fanouts`, oldest first.
+    fanout_floods_order: VecDeque<(NodeId, u64)>,
+    /// Resolver filling the gaps of incomplete routes from their destination.
+    next_hop_resolver: Option<Hook<NextHopResolver>>,
+    /// Whether every send to a neighbor is reported with attempt and result telemetry events.
+    send_attempt_events: bool,
+    /// Whether the flood responses about to reach their initiator are aggregated.
+    initiator_response_dedup: bool,
+    /// Path traces of the responses forwarded to each adjacent initiator, per tracked
+    /// `(initiator_id, flood_id)`.
+    initiator_responses: HashMap<(NodeId, u64), HashSet<Vec<NodeId>>>,
+    /// Tracked floods of `initiator_responses`, oldest first.
+    initiator_floods_order: VecDeque<(NodeId, u64)>,
+    /// Directional drop rates of the links to some neighbors (symmetric and lossless otherwise).
+    link_drop_rates: HashMap<NodeId, LinkDropRates>,
+    /// Minimum interval between two packets sent to some neighbors.
+    neighbor_min_gaps: HashMap<NodeId, Duration>,
+    /// Earliest instant at which the next packet can be sent to each neighbor with a minimum gap.
+    next_send_slots: HashMap<NodeId, Instant>,
+    /// Packets delayed by a minimum gap, with the instant they are due and the neighbor channel.
+    deferred_sends: VecDeque<(Instant, NodeId, Sender<Packet>, Packet)>,
+    /// Delay before the single retry of a fragment drawn as dropped (`None`: NACK immediately).
+    drop_retry_delay: Option<Duration>,
+    /// Fragments drawn as dropped, waiting for their retry, with the instant they are due.
+    pending_retries: Vec<(Instant, Packet)>,
+    /// Whether each of the last forwarded fragments was a transit forward (between two drones).
+    recent_roles: VecDeque<bool>,
+    /// Channel receiving a copy of every event, tagged with the ID of the drone.
+    tagged_event_sender: Option<Sender<(NodeId, DroneEvent)>>,
+    /// Policy deciding what happens to the control packets with an invalid routing header.
+    invalid_control_policy: InvalidControlPolicy,
+    /// Observed drop ratio above which a warning is logged, with its observation window.
+    drop_rate_warning: Option<(f64, Duration)>,
+    /// Start of the current drop ratio window, and the fragments dropped and forwarded in it.
+    drop_rate_window: Option<(Instant, u64, u64)>,
+    /// Whether the warning was already logged in the current drop ratio window.
+    drop_rate_warned: bool,
+    /// Whether flood requests skip every neighbor already in their path trace, not only the sender.
+    skip_traced_flood_neighbors: bool,
+    /// Whether the received fragments are buffered instead of being forwarded.
+    held: bool,
+    /// Maximum number of fragments buffered while the drone is held.
+    hold_capacity: usize,
+    /// Fragments buffered while the drone is held, in arrival order.
+    held_fragments: VecDeque<Packet>,
+    /// Order in which the fragments of a session are forwarded.
+    fragment_order: FragmentOrder,
+    /// Fragments of each session waiting to be forwarded in the fragment order, in arrival order.
+    reorder_buffers: HashMap<u64, Vec<Packet>>,
+    /// Policy deciding what happens to the packets of the reserved session 0.
+    reserved_session_policy: ReservedSessionPolicy,
 }
 
 impl Drone for GetDroned {
@@ -90,6 +592,8 @@ impl Drone for GetDroned {
             pdr,
             packet_send.keys()
         );
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let now = clock.now();
 
         GetDroned {
             id,
@@ -100,30 +604,179 @@ impl Drone for GetDroned {
             command_receiver: controller_recv,
             is_crashed: false,
             received_floods: HashSet::new(),
+            clock,
+            track_send_block_time: false,
+            send_block_time: Duration::ZERO,
+            nack_aggregation_window: None,
+            recent_nacks: HashMap::new(),
+            default_route: None,
+            rng: StdRng::from_entropy(),
+            header_corruption_rate: 0.0,
+            telemetry_sender: None,
+            leaf_flood_policy: LeafFloodPolicy::default(),
+            command_history_capacity: 0,
+            command_history: VecDeque::new(),
+            stats: DroneStats::default(),
+            on_flood_response: None,
+            max_active_sessions: None,
+            session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+            active_sessions: HashMap::new(),
+            max_fragment_bytes: None,
+            sent_to: HashSet::new(),
+            received_from: HashSet::new(),
+            stats_summary_interval: None,
+            last_stats_summary: None,
+            last_summary_stats: DroneStats::default(),
+            pending_flush_policy: PendingFlushPolicy::default(),
+            neighbor_receivers: HashMap::new(),
+            on_isolated: None,
+            extension_command_receiver: never(),
+            startup_jitter: None,
+            held_flood_policy: HeldFloodPolicy::default(),
+            outcome_history_capacity: 0,
+            recent_outcomes: VecDeque::new(),
+            crashed_neighbors: HashSet::new(),
+            path_traces: None,
+            log_file_per_drone: false,
+            strict_flood_validation: false,
+            #[cfg(any(test, feature = "test-utils"))]
+            timeline: None,
+            max_route_hops: Some(DEFAULT_MAX_ROUTE_HOPS),
+            command_reorder_batch: None,
+            event_batch_sender: None,
+            event_batch_size: 1,
+            pending_events: Vec::new(),
+            neighbor_types: HashMap::new(),
+            nack_rate_limit: None,
+            nack_tokens: 0.0,
+            nack_tokens_refilled_at: None,
+            recently_removed: HashMap::new(),
+            removed_neighbor_retention: DEFAULT_REMOVED_NEIGHBOR_RETENTION,
+            tap: None,
+            health_thresholds: HealthThresholds::default(),
+            last_packet_at: now,
+            command_channel_disconnected: false,
+            packet_channel_disconnected: false,
+            flood_jitter: None,
+            pending_floods: Vec::new(),
+            first_hop_correction: false,
+            route_lengths: RouteLengthHistogram::default(),
+            forward_worker_count: 0,
+            forward_workers: Vec::new(),
+            forward_worker_threads: Vec::new(),
+            forward_results: never(),
+            on_pdr_change: None,
+            bounded_channels: false,
+            session_losses: HashMap::new(),
+            loss_sessions_order: VecDeque::new(),
+            flood_response_dedup: false,
+            forwarded_flood_responses: HashSet::new(),
+            forwarded_responses_order: VecDeque::new(),
+            flood_trace_check: false,
+            flood_trace_lengths: HashMap::new(),
+            flood_traces_order: VecDeque::new(),
+            event_log_capacity: 0,
+            event_log: VecDeque::new(),
+            drop_model: DropModel::default(),
+            burst_position: 0,
+            flood_fanouts: HashMap::new(),
+            fanout_floods_order: VecDeque::new(),
+            next_hop_resolver: None,
+            send_attempt_events: false,
+            initiator_response_dedup: false,
+            initiator_responses: HashMap::new(),
+            initiator_floods_order: VecDeque::new(),
+            link_drop_rates: HashMap::new(),
+            neighbor_min_gaps: HashMap::new(),
+            next_send_slots: HashMap::new(),
+            deferred_sends: VecDeque::new(),
+            drop_retry_delay: None,
+            pending_retries: Vec::new(),
+            recent_roles: VecDeque::new(),
+            tagged_event_sender: None,
+            invalid_control_policy: InvalidControlPolicy::default(),
+            drop_rate_warning: None,
+            drop_rate_window: None,
+            drop_rate_warned: false,
+            skip_traced_flood_neighbors: false,
+            held: false,
+            hold_capacity: DEFAULT_HOLD_CAPACITY,
+            held_fragments: VecDeque::new(),
+            fragment_order: FragmentOrder::default(),
+            reorder_buffers: HashMap::new(),
+            reserved_session_policy: ReservedSessionPolicy::default(),
         }
     }
 
     /// Starts the drone execution loop.
     ///
     /// The drone will listen for incoming packets and commands. If a crash command is received, it stops execution.
-
-    /// Starts the drone execution loop.
+    /// It also stops, without a crash, once both its command and its packet channels are disconnected.
     ///
-    /// The drone will listen for incoming packets and commands. If a crash command is received, it stops execution.
+    /// # Notes
+    /// After a crash, the drone drains its packet channel until every sender disconnected,
+    /// processing the backlog strictly in arrival order and applying only the `AddSender` and
+    /// `RemoveSender` commands (see `drain_after_crash`), so that the NACKs and shortcuts of a
+    /// crash are reproducible.
     fn run(&mut self) {
+        CURRENT_DRONE.with(|drone| drone.set(Some(self.id)));
         info!("Drone {} started execution.", self.id);
         if cfg!(feature = "log") {
-            let _ = init_logger();
+            let _ = if self.log_file_per_drone {
+                init_logger_for_drone(self.id)
+            } else {
+                init_logger()
+            };
         }
+        let mut neighbor_packets = self.spawn_neighbor_forwarders();
+        self.spawn_forward_workers();
+        // With a startup jitter, the ticker first fires once after the random delay, then periodically.
+        let mut ticker_started = self.startup_jitter.is_none();
+        let mut ticker = if !self.needs_tick() {
+            never()
+        } else if ticker_started {
+            tick(TICK_INTERVAL)
+        } else {
+            after(self.startup_delay())
+        };
         loop {
             select_biased! {
                 recv(self.command_receiver) -> command => {
                     match command {
                         Ok(command) => {
                             info!("Drone {} received a command: {:?}", self.id, command);
-                            self.process_command(command);
+                            if self.command_reorder_batch.is_some() {
+                                self.process_reordered_commands(command);
+                            } else {
+                                self.process_command(command);
+                            }
+                            if self.is_crashed {
+                                self.drain_after_crash(&mut neighbor_packets);
+                                return;
+                            }
+                        },
+                        Err(e) => {
+                            warn!("Drone {} failed to receive a command: {:?}", self.id, e);
+                            self.command_channel_disconnected = true;
+                            self.command_receiver = never();
+                            if self.packet_channel_disconnected {
+                                warn!("Drone {} lost all its channels, terminating", self.id);
+                                self.finish_execution();
+                                return;
+                            }
+                        },
+                    }
+                },
+                recv(self.extension_command_receiver) -> command => {
+                    match command {
+                        Ok(command) => {
+                            info!("Drone {} received a command: {:?}", self.id, command);
+                            self.process_extension_command(command);
+                        },
+                        Err(_) => {
+                            warn!("Drone {} extension command channel disconnected", self.id);
+                            self.extension_command_receiver = never();
                         },
-                        Err(e) => warn!("Drone {} failed to receive a command: {:?}", self.id, e),
                     }
                 },
                 recv(self.receiver) -> packet => {
@@ -134,259 +787,3379 @@ impl Drone for GetDroned {
                         },
                         Err(e) => {
                             if self.is_crashed {
-                                info!("Drone {} finished execution.", self.id); // Logging ici
+                                self.finish_execution();
+                                return;
+                            }
+                            warn!("Drone {} failed to receive a packet: {:?}", self.id, e);
+                            self.packet_channel_disconnected = true;
+                            self.receiver = never();
+                            if self.command_channel_disconnected {
+                                warn!("Drone {} lost all its channels, terminating", self.id);
+                                self.finish_execution();
                                 return;
-                            } else {
-                                warn!("Drone {} failed to receive a packet: {:?}", self.id, e);
                             }
                         },
                     }
                 },
+                recv(neighbor_packets) -> packet => {
+                    match packet {
+                        Ok((neighbor, packet)) => {
+                            info!("Drone {} received a packet from {}: {:?}", self.id, neighbor, packet);
+                            self.process_packet_from(neighbor, packet);
+                        },
+                        // Every neighbor disconnected: stop polling the channel.
+                        Err(_) => neighbor_packets = never(),
+                    }
+                },
+                recv(self.forward_results) -> result => {
+                    match result {
+                        Ok(result) => self.settle_forward(result),
+                        Err(_) => self.forward_results = never(),
+                    }
+                },
+                recv(ticker) -> _ => {
+                    if !ticker_started {
+                        ticker = tick(TICK_INTERVAL);
+                        ticker_started = true;
+                    }
+                    self.on_tick();
+                },
             }
         }
     }
 }
 
-// * No function should be public (you can use only run and new functions from external)
 impl GetDroned {
-    /// Adds a neighboring sender to the drone's list of known neighbors.
+    /// Replaces the clock used by the drone for its time measurements.
     ///
     /// # Parameters
-    /// - `id`: The unique ID of the neighboring node.
-    /// - `sender`: The communication channel (`Sender<Packet>`) to send packets to the neighbor.
-    fn add_neighbor_sender(&mut self, id: u8, sender: Sender<Packet>) {
-        self.packet_senders.insert(id, sender);
+    /// - `clock`: The clock to use, e.g. a shared `ManualClock` in tests.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+        self.last_packet_at = self.clock.now();
     }
 
-    /// Removes a neighboring sender from the drone's list of known neighbors.
+    /// Enables or disables the measurement of the time spent blocked while sending packets.
+    /// Disabled by default to avoid reading the clock on every send.
+    pub fn set_send_block_tracking(&mut self, enabled: bool) {
+        self.track_send_block_time = enabled;
+    }
+
+    /// Returns the total time spent blocked on neighbor channels while sending packets.
+    /// A high value indicates downstream congestion. Always zero if tracking is disabled.
+    pub fn total_send_block_time(&self) -> Duration {
+        self.send_block_time
+    }
+
+    /// Checks whether the drone could forward a packet with the given routing header,
+    /// i.e. whether it is the current hop of the header and the next hop is one of its neighbors.
+    /// This is a read-only check, nothing is sent.
     ///
     /// # Parameters
-    /// - `id`: The unique ID of the neighboring node to be removed.
-    fn remove_neighbor_sender(&mut self, id: NodeId) {
-        self.packet_senders.remove(&id);
+    /// - `header`: The routing header to check.
+    pub fn can_route(&self, header: &SourceRoutingHeader) -> bool {
+        self.validate_route(header).is_ok()
     }
 
-    /// Sends a packet to a specific neighboring node.
+    /// Sets the default route of the drone.
     ///
-    /// ### Parameters
-    /// - `p`: The packet to be sent.
-    /// - `dest_id`: The ID of the neighboring node to which the packet will be sent.
+    /// When the next hop of a packet is not a neighbor, the packet is forwarded to the gateway
+    /// (if it is a neighbor) instead of being NACKed: the gateway is inserted in the routing header
+    /// as the next hop, so that it can forward the packet to the intended node.
     ///
-    /// ### Returns
-    /// - `Ok(())` if the packet was sent successfully,
-    /// - `Err(String)` if there was an error sending the packet.
-    fn send_packet(&self, mut p: Packet) {
-        let original_packet = p.clone();
-        if let Some(next_hop) = p.routing_header.next_hop() {
-            p.routing_header.hop_index += 1;
-            if let Some(sender) = self.packet_senders.get(&next_hop) {
-                match sender.send(p.clone()) {
-                    Ok(_) => self.send_event(DroneEvent::PacketSent(p.clone())),
-                    Err(_) => match p.clone().pack_type {
-                        PacketType::FloodRequest(_flood_request) => self
-                            .send_nack(original_packet.clone(), NackType::ErrorInRouting(self.id)),
-                        PacketType::MsgFragment(_fragment) => self
-                            .send_nack(original_packet.clone(), NackType::ErrorInRouting(self.id)),
-                        _ => self.send_event(DroneEvent::ControllerShortcut(p.clone())),
-                    },
-                }
-            }
-        } else {
-            self.send_event(DroneEvent::ControllerShortcut(p.clone()));
-        }
+    /// # Parameters
+    /// - `gateway`: The ID of the gateway neighbor, or `None` for strict source routing (default).
+    pub fn set_default_route(&mut self, gateway: Option<NodeId>) {
+        self.default_route = gateway;
     }
 
-    /// Creates and sends a NACK packet to notify the sender of an error or specific event.
-    /// Used to signal issues such as unexpected recipients, routing errors, or dropped packets.
+    /// Seeds the random number generator of the drone, making its random decisions reproducible.
     ///
     /// # Parameters
-    /// - `packet`: The original packet that caused the issue.
-    /// - `nack_type`: The type of error or event that occurred.
-    fn send_nack(&self, mut packet: Packet, nack_type: NackType) {
-        let nack = Nack {
-            fragment_index: packet.get_fragment_index(),
-            nack_type,
-        };
-        match nack_type {
-            NackType::UnexpectedRecipient(_) => {
-                for i in 0..packet.routing_header.hops.len() {
-                    if self
-                        .packet_senders
-                        .contains_key(&packet.routing_header.hops[i])
-                    {
-                        packet.routing_header.hop_index = i + 1;
-                        break;
-                    }
-                }
-            }
-            _ => {}
-        }
-        if let Some(routing_header) = packet
-            .routing_header
-            .sub_route(..packet.routing_header.hop_index + 1)
-        {
-            self.send_packet(Packet::new_nack(
-                routing_header.get_reversed(),
-                packet.session_id,
-                nack,
-            ));
-        }
+    /// - `seed`: The seed of the generator.
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
     }
 
-    fn send_flood_request(&self, packet: Packet, received_from: NodeId) {
-        for neighbor in self.packet_senders.clone() {
-            if neighbor.0 != received_from {
-                match neighbor.1.send(packet.clone()) {
-                    Ok(_) => self.send_event(DroneEvent::PacketSent(packet.clone())),
-                    Err(_) => {}
-                }
-            }
-        }
+    /// Sets the channel where GetDroned-specific telemetry events are sent.
+    ///
+    /// # Parameters
+    /// - `sender`: The telemetry channel, or `None` to disable telemetry (default).
+    pub fn set_telemetry_sender(&mut self, sender: Option<Sender<TelemetryEvent>>) {
+        self.telemetry_sender = sender;
     }
 
-    /// Validates whether the received packet is correctly addressed and ready for processing.
-    /// If the packet is invalid, sends an appropriate NACK to notify the sender.
+    /// Sets a channel receiving a copy of every event of the drone, tagged with its ID, so that a
+    /// controller can multiplex many drones onto one channel with a clear attribution. The events
+    /// are still sent to the standard event channel; the tagged copies are never batched.
     ///
     /// # Parameters
-    /// - `packet`: The packet to validate.
+    /// - `sender`: The tagged event channel, or `None` to disable the copies (default).
+    pub fn set_tagged_event_sender(&mut self, sender: Option<Sender<(NodeId, DroneEvent)>>) {
+        self.tagged_event_sender = sender;
+    }
+
+    /// Sets the probability of flipping a bit of the routing header of each forwarded packet,
+    /// to test how the network handles corrupted routes. Each corruption is logged and reported
+    /// as a `TelemetryEvent::HeaderCorrupted`.
     ///
-    /// # Returns
-    /// - `true` if the packet is valid and can proceed to processing.
-    /// - `false` if the packet is invalid, and a NACK has been sent.
-    fn validate_packet(&self, mut packet: Packet) -> Result<(), NackType> {
-        if packet.routing_header.hops[packet.routing_header.hop_index] != self.id {
-            return Err(NackType::UnexpectedRecipient(self.id));
-        }
-        packet.routing_header.hop_index += 1;
-        if packet.routing_header.hop_index == packet.routing_header.hops.len() {
-            return Err(NackType::DestinationIsDrone);
-        }
-        let next_hop = packet.routing_header.hops[packet.routing_header.hop_index];
-        if !self.packet_senders.contains_key(&next_hop) {
-            return Err(NackType::ErrorInRouting(next_hop));
-        }
-        Ok(())
+    /// # Parameters
+    /// - `rate`: The corruption probability (0.00 to 1.00, default 0.00).
+    pub fn set_header_corruption_rate(&mut self, rate: f32) {
+        self.header_corruption_rate = rate;
     }
 
-    /// Processes a received packet by determining its type and delegating its handling.
-    /// First validates the packet, then handles it according to its specific type (e.g., message fragment or flood request).
+    /// Sets the policy deciding when the drone answers a flood request as a leaf of the network
+    /// instead of forwarding it.
     ///
     /// # Parameters
-    /// - `packet`: The packet to process.
-    fn process_packet(&mut self, packet: Packet) {
-        match packet.clone().pack_type {
-            PacketType::MsgFragment(_fragment) => match self.validate_packet(packet.clone()) {
-                Ok(()) => self.process_fragment(packet.clone()),
-                Err(nack_type) => self.send_nack(packet, nack_type),
-            },
-            PacketType::FloodRequest(flood_request) => {
-                self.process_flood_request(packet, flood_request)
-            }
-            //Ack, Nack or FloodResponse
-            _ => match self.validate_packet(packet.clone()) {
-                Ok(()) => self.send_packet(packet.clone()),
-                Err(_) => self.send_event(DroneEvent::ControllerShortcut(packet)),
-            },
-        }
+    /// - `policy`: The leaf policy (default: `LeafFloodPolicy::RespondIfNoOtherNeighbor`).
+    pub fn set_leaf_flood_policy(&mut self, policy: LeafFloodPolicy) {
+        self.leaf_flood_policy = policy;
     }
 
-    /// Handles a message fragment by forwarding it to the next hop.
-    /// Simulates packet drop based on the drone's packet drop rate, sending a NACK if the packet is dropped.
+    /// Sets the maximum number of commands kept in the command history.
+    /// When the history is full, the oldest command is overwritten by the new one.
     ///
     /// # Parameters
-    /// - `packet`: The message fragment to process.
-    /// - `next_hop`: The ID of the next node in the routing path.
-    fn process_fragment(&self, packet: Packet) {
-        if self.is_crashed {
-            self.send_nack(packet.clone(), NackType::ErrorInRouting(self.id));
-            return;
+    /// - `capacity`: The size of the history, 0 to disable it (default).
+    pub fn set_command_history_capacity(&mut self, capacity: usize) {
+        self.command_history_capacity = capacity;
+        while self.command_history.len() > capacity {
+            self.command_history.pop_front();
         }
-        if self.packet_drop_rate > 0.0 && rand::random::<f32>() < self.packet_drop_rate {
-            self.send_nack(packet.clone(), NackType::Dropped);
-            self.send_event(DroneEvent::PacketDropped(packet.clone()));
-            return;
-        }
-        self.send_packet(packet)
     }
 
-    /// Processes a flood request packet by determining the appropriate action based on the flood path
-    /// and the drone's neighbors. The drone can either generate a response or forward the request.
+    /// Returns the last commands received by the drone, with the time they were received at,
+    /// oldest first.
+    pub fn command_history(&self) -> &VecDeque<(Instant, CommandRecord)> {
+        &self.command_history
+    }
+
+    /// Forces the crash state of the drone, to test crashed behaviors without orchestrating
+    /// the `Crash` command and the channel teardown. Only available in tests or with the
+    /// `test-utils` feature.
     ///
     /// # Parameters
-    /// - `packet`: The flood request packet being processed.
-    /// - `flood_request`: The flood request data extracted from the packet.
+    /// - `crashed`: The new crash state.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn set_crashed(&mut self, crashed: bool) {
+        self.is_crashed = crashed;
+    }
+
+    /// Applies a command directly, as if it was received on the command channel, so that tests
+    /// can check its effect without wiring the channel and running the drone loop. Only available
+    /// in tests or with the `test-utils` feature.
     ///
-    /// # Behavior
-    /// - If the drone is already part of the flood path (`path_trace`), or it has no neighbors
-    ///   other than the sender, it generates a response and sends it to the sender.
-    /// - Otherwise, the drone forwards the flood request to all neighbors except the sender.
-    fn process_flood_request(&mut self, mut packet: Packet, mut flood_request: FloodRequest) {
-        let sender_id = match flood_request.path_trace.last() {
-            Some((id, _)) => id.clone(),
-            None => flood_request.initiator_id.clone(),
-        };
+    /// # Parameters
+    /// - `command`: The command to apply.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn apply_command(&mut self, command: DroneCommand) {
+        self.process_command(command);
+    }
 
-        flood_request.increment(self.id, NodeType::Drone);
+    /// Takes every packet currently waiting on the packet channel of the drone, without
+    /// processing it, so that tests can check exactly what was queued. Only available in tests
+    /// or with the `test-utils` feature.
+    ///
+    /// # Returns
+    /// The queued packets, in arrival order.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn drain_available(&self) -> Vec<Packet> {
+        self.receiver.try_iter().collect()
+    }
 
-        if self
-            .received_floods
-            .contains(&(flood_request.initiator_id, flood_request.flood_id))
-            || self.packet_senders.len() == 1
-        {
-            let response = flood_request.generate_response(packet.session_id);
-            self.send_packet(response);
-        } else {
-            self.received_floods
-                .insert((flood_request.initiator_id, flood_request.flood_id));
-            packet.pack_type = PacketType::FloodRequest(flood_request);
-            self.send_flood_request(packet.clone(), sender_id);
+    /// Starts recording every side effect of the drone (packets sent, NACKs generated, events
+    /// emitted and packet outcomes) into a single ordered timeline, discarding any previous one.
+    /// Only available in tests or with the `test-utils` feature.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn start_recording(&mut self) {
+        self.timeline = Some(Vec::new());
+    }
+
+    /// Takes the timeline recorded so far, leaving an empty one if recording is on.
+    /// Compare it with `recording::assert_timeline`.
+    /// Only available in tests or with the `test-utils` feature.
+    ///
+    /// # Returns
+    /// The recorded entries, oldest first (empty if recording was never started).
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn take_timeline(&mut self) -> Vec<TimelineEntry> {
+        self.timeline
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Returns a snapshot of all the current settings of the drone.
+    pub fn config(&self) -> DroneConfigSnapshot {
+        let mut neighbors: Vec<NodeId> = self.packet_senders.keys().copied().collect();
+        neighbors.sort_unstable();
+        let mut link_drop_rates: Vec<(NodeId, LinkDropRates)> = self
+            .link_drop_rates
+            .iter()
+            .map(|(id, rates)| (*id, *rates))
+            .collect();
+        link_drop_rates.sort_unstable_by_key(|(id, _)| *id);
+        let mut neighbor_min_gaps: Vec<(NodeId, Duration)> = self
+            .neighbor_min_gaps
+            .iter()
+            .map(|(id, gap)| (*id, *gap))
+            .collect();
+        neighbor_min_gaps.sort_unstable_by_key(|(id, _)| *id);
+        DroneConfigSnapshot {
+            id: self.id,
+            neighbors,
+            packet_drop_rate: self.packet_drop_rate,
+            header_corruption_rate: self.header_corruption_rate,
+            default_route: self.default_route,
+            leaf_flood_policy: self.leaf_flood_policy,
+            nack_aggregation_window: self.nack_aggregation_window,
+            max_active_sessions: self.max_active_sessions,
+            session_idle_timeout: self.session_idle_timeout,
+            max_fragment_bytes: self.max_fragment_bytes,
+            command_history_capacity: self.command_history_capacity,
+            send_block_tracking: self.track_send_block_time,
+            stats_summary_interval: self.stats_summary_interval,
+            pending_flush_policy: self.pending_flush_policy,
+            startup_jitter: self.startup_jitter,
+            held_flood_policy: self.held_flood_policy,
+            outcome_history_capacity: self.outcome_history_capacity,
+            strict_flood_validation: self.strict_flood_validation,
+            max_route_hops: self.max_route_hops,
+            command_reorder_batch: self.command_reorder_batch,
+            event_batch_size: self.event_batch_size,
+            nack_rate_limit: self.nack_rate_limit,
+            removed_neighbor_retention: self.removed_neighbor_retention,
+            tap: self.tap,
+            health_thresholds: self.health_thresholds,
+            flood_jitter: self.flood_jitter,
+            first_hop_correction: self.first_hop_correction,
+            forward_workers: self.forward_worker_count,
+            bounded_channels: self.bounded_channels,
+            flood_response_dedup: self.flood_response_dedup,
+            flood_trace_check: self.flood_trace_check,
+            event_log_capacity: self.event_log_capacity,
+            drop_model: self.drop_model,
+            send_attempt_events: self.send_attempt_events,
+            initiator_response_dedup: self.initiator_response_dedup,
+            link_drop_rates,
+            neighbor_min_gaps,
+            drop_retry_delay: self.drop_retry_delay,
+            invalid_control_policy: self.invalid_control_policy,
+            drop_rate_warning: self.drop_rate_warning,
+            skip_traced_flood_neighbors: self.skip_traced_flood_neighbors,
+            hold_capacity: self.hold_capacity,
+            fragment_order: self.fragment_order,
+            reserved_session_policy: self.reserved_session_policy,
         }
     }
 
-    /// Processes a command sent to the drone, modifying its state or behavior accordingly.
+    /// Applies a full configuration profile at once, replacing every setting it covers,
+    /// and confirms it with a single `TelemetryEvent::ProfileApplied`.
     ///
     /// # Parameters
-    /// - `command`: A `DroneCommand` enum representing the action to be executed.
+    /// - `profile`: The profile to apply.
+    pub fn apply_profile(&mut self, profile: DroneProfile) {
+        info!("Drone {} applies the profile {:?}", self.id, profile);
+        self.packet_drop_rate = profile.packet_drop_rate;
+        self.header_corruption_rate = profile.header_corruption_rate;
+        self.default_route = profile.default_route;
+        self.leaf_flood_policy = profile.leaf_flood_policy;
+        self.set_nack_aggregation_window(profile.nack_aggregation_window);
+        self.set_max_active_sessions(profile.max_active_sessions);
+        self.session_idle_timeout = profile.session_idle_timeout;
+        self.pending_flush_policy = profile.pending_flush_policy;
+        self.held_flood_policy = profile.held_flood_policy;
+        self.set_drop_model(profile.drop_model);
+        self.link_drop_rates = profile.link_drop_rates.iter().copied().collect();
+        self.invalid_control_policy = profile.invalid_control_policy;
+        self.reserved_session_policy = profile.reserved_session_policy;
+        self.send_telemetry(TelemetryEvent::ProfileApplied(profile));
+    }
+
+    /// Returns the neighbors that are potentially linked asymmetrically to the drone, sorted:
+    /// the drone sent packets to them, but never received a packet from them.
     ///
-    /// # Supported Commands
-    /// - `DroneCommand::AddSender(id, sender)`: Adds a neighboring sender to the drone's network.
-    /// - `DroneCommand::Crash`: Marks the drone as crashed, disabling its functionality.
-    /// - `DroneCommand::SetPacketDropRate(pdr)`: Sets the packet drop rate to simulate unreliable communication.
-    /// - `DroneCommand::RemoveSender(id)`: Removes a neighboring sender from the drone's network.
-    fn process_command(&mut self, command: DroneCommand) {
-        match command {
-            DroneCommand::AddSender(id, sender) => {
-                self.add_neighbor_sender(id, sender);
-            }
-            DroneCommand::Crash => {
-                self.is_crashed = true;
-            }
-            DroneCommand::SetPacketDropRate(pdr) => {
-                self.packet_drop_rate = pdr;
-            }
-            DroneCommand::RemoveSender(id) => {
-                self.remove_neighbor_sender(id);
-            }
-        }
+    /// The senders of the incoming packets are inferred from their routing header (or from the
+    /// path trace of flood requests), so this is a heuristic which gets more reliable over time.
+    pub fn asymmetric_links(&self) -> Vec<NodeId> {
+        let mut links: Vec<NodeId> = self
+            .sent_to
+            .iter()
+            .filter(|id| self.packet_senders.contains_key(id) && !self.received_from.contains(id))
+            .copied()
+            .collect();
+        links.sort_unstable();
+        links
     }
 
-    /// Sends an event to Simulation Controller.
+    /// Returns the activity counters of the drone.
+    pub fn stats(&self) -> &DroneStats {
+        &self.stats
+    }
+
+    /// Returns the highest flood ID the drone has seen from an initiator, so that the initiator
+    /// can pick a higher one for its next flood: a reused flood ID would be answered as already
+    /// received instead of being forwarded.
     ///
     /// # Parameters
-    /// - `event`: A `DroneEvent` representing the event to be sent (e.g., packet sent, dropped).
-    ///
-    /// # Behavior
-    /// - Attempts to send the event via the `event_sender` channel.
-    /// - If the sending operation fails (e.g., the channel is closed), logs an error message with the failure reason.
+    /// - `initiator`: The ID of the flood initiator.
     ///
-    /// # Notes
+    /// # Returns
+    /// The highest flood ID received from the initiator, or `None` if none was received.
+    pub fn max_flood_id(&self, initiator: NodeId) -> Option<u64> {
+        self.received_floods
+            .iter()
+            .filter(|(id, _)| *id == initiator)
+            .map(|(_, flood_id)| *flood_id)
+            .max()
+    }
+
+    /// Exports the floods the drone has received, so that its flood memory can be persisted
+    /// and restored with `import_received_floods`, e.g. across a reboot of the simulation.
+    ///
+    /// # Returns
+    /// The `(initiator_id, flood_id)` keys of the received floods, sorted.
+    pub fn export_received_floods(&self) -> Vec<(NodeId, u64)> {
+        let mut floods: Vec<(NodeId, u64)> = self.received_floods.iter().copied().collect();
+        floods.sort_unstable();
+        floods
+    }
+
+    /// Imports floods exported with `export_received_floods`: they are answered as already
+    /// received instead of being forwarded again. The floods already known are kept.
+    ///
+    /// # Parameters
+    /// - `floods`: The `(initiator_id, flood_id)` keys of the floods.
+    pub fn import_received_floods(&mut self, floods: &[(NodeId, u64)]) {
+        self.received_floods.extend(floods.iter().copied());
+    }
+
+    /// Forgets every flood the drone has received, so that new requests of these floods are
+    /// forwarded again.
+    pub fn clear_received_floods(&mut self) {
+        self.received_floods.clear();
+    }
+
+    /// Returns the health of the drone, combining its crash state, its neighbors, its command
+    /// channel, its fragment drop ratio and the time since it last received a packet, against
+    /// its health thresholds.
+    ///
+    /// # Notes
+    /// The level is `Dead` once the drone crashed, `Degraded` if any adverse condition is
+    /// detected and `Healthy` otherwise.
+    pub fn health(&self) -> HealthStatus {
+        let mut reasons = Vec::new();
+        if self.is_crashed {
+            reasons.push("crashed".to_string());
+        }
+        if self.packet_senders.is_empty() {
+            reasons.push("no neighbors".to_string());
+        } else if self
+            .packet_senders
+            .keys()
+            .all(|id| self.crashed_neighbors.contains(id))
+        {
+            reasons.push("all neighbors crashed".to_string());
+        }
+        if self.command_channel_disconnected {
+            reasons.push("command channel disconnected".to_string());
+        }
+        if self.packet_channel_disconnected {
+            reasons.push("packet channel disconnected".to_string());
+        }
+        if self.held {
+            reasons.push(format!(
+                "held with {} buffered fragments",
+                self.held_fragments.len()
+            ));
+        }
+        let handled = self.stats.fragments_forwarded + self.stats.fragments_dropped;
+        if handled > 0 {
+            let drop_ratio = self.stats.fragments_dropped as f64 / handled as f64;
+            if drop_ratio > self.health_thresholds.max_drop_ratio {
+                reasons.push(format!(
+                    "drop ratio {:.2} over {:.2}",
+                    drop_ratio, self.health_thresholds.max_drop_ratio
+                ));
+            }
+        }
+        if let Some(max_idle) = self.health_thresholds.max_idle {
+            let idle = self
+                .clock
+                .now()
+                .saturating_duration_since(self.last_packet_at);
+            if idle > max_idle {
+                reasons.push(format!("idle for {:?} over {:?}", idle, max_idle));
+            }
+        }
+        let level = if self.is_crashed {
+            DroneHealth::Dead
+        } else if reasons.is_empty() {
+            DroneHealth::Healthy
+        } else {
+            DroneHealth::Degraded
+        };
+        HealthStatus {
+            healthy: reasons.is_empty(),
+            level,
+            reasons,
+        }
+    }
+
+    /// Returns the histogram of the route lengths of the packets processed by the drone.
+    /// Many long routes indicate inefficient paths or routing loops.
+    pub fn route_length_histogram(&self) -> &RouteLengthHistogram {
+        &self.route_lengths
+    }
+
+    /// Returns how many fragments of a session the drone did not forward, whatever the reason
+    /// (drop rate, NACK, failed send), so that the controller can attribute the loss of a
+    /// session to each hop. Only the last 1024 sessions with losses are tracked.
+    ///
+    /// # Parameters
+    /// - `session_id`: The session.
+    ///
+    /// # Returns
+    /// The number of lost fragments, or `None` if the drone lost none (or no longer tracks the session).
+    pub fn session_loss(&self, session_id: u64) -> Option<u32> {
+        self.session_losses.get(&session_id).copied()
+    }
+
+    /// Returns how many neighbors the drone forwarded a flood request to (excluding the neighbor
+    /// it received the request from), quantifying its contribution to the coverage of the flood.
+    /// Only the last 1024 floods forwarded are tracked.
+    ///
+    /// # Parameters
+    /// - `initiator_id`: The initiator of the flood.
+    /// - `flood_id`: The ID of the flood.
+    ///
+    /// # Returns
+    /// The fanout, or `None` if the drone did not forward the flood (or no longer tracks it).
+    pub fn flood_fanout(&self, initiator_id: NodeId, flood_id: u64) -> Option<usize> {
+        self.flood_fanouts.get(&(initiator_id, flood_id)).copied()
+    }
+
+    /// Summarizes the role of the drone in its last 256 forwarded fragments: transit between two
+    /// drones, or adjacent to a client or a server (the previous or the next hop). Neighbors whose
+    /// type is neither declared nor learned from a flood are counted as drones.
+    pub fn role_summary(&self) -> RoleSummary {
+        let transit = self.recent_roles.iter().filter(|transit| **transit).count() as u64;
+        RoleSummary {
+            transit,
+            endpoint_adjacent: self.recent_roles.len() as u64 - transit,
+        }
+    }
+
+    /// Returns a fingerprint of the neighbor set of the drone: a hash of the sorted neighbor IDs,
+    /// stable across runs, so that the controller can detect topology changes by comparing the
+    /// fingerprints of a drone over time.
+    pub fn topology_fingerprint(&self) -> u64 {
+        let mut neighbors: Vec<u64> = self
+            .packet_senders
+            .keys()
+            .map(|id| u64::from(*id))
+            .collect();
+        neighbors.sort_unstable();
+        stable_hash(&neighbors)
+    }
+
+    /// Clears every activity measurement of the drone at once: the counters of `stats` (and the
+    /// averages derived from them), the baseline of the periodic summary, the route length
+    /// histogram, the per-session losses, the role summary, the send block time, the flood
+    /// fanouts, the drop rate warning window and the event log.
+    /// Settings and neighbor state are kept.
+    pub fn reset_stats(&mut self) {
+        self.stats = DroneStats::default();
+        self.last_summary_stats = DroneStats::default();
+        self.route_lengths = RouteLengthHistogram::default();
+        self.session_losses.clear();
+        self.loss_sessions_order.clear();
+        self.recent_roles.clear();
+        self.send_block_time = Duration::ZERO;
+        self.flood_fanouts.clear();
+        self.fanout_floods_order.clear();
+        self.drop_rate_window = None;
+        self.drop_rate_warned = false;
+        self.event_log.clear();
+    }
+
+    /// Returns the neighbors a flood request received from a node would be forwarded to,
+    /// without sending anything: every current neighbor except the sender.
+    /// Whether the drone forwards the request at all depends on its `LeafFloodPolicy`.
+    ///
+    /// # Parameters
+    /// - `received_from`: The node the flood request would be received from.
+    ///
+    /// # Returns
+    /// The IDs of the target neighbors, sorted.
+    pub fn flood_targets(&self, received_from: NodeId) -> Vec<NodeId> {
+        let mut targets: Vec<NodeId> = self
+            .packet_senders
+            .keys()
+            .copied()
+            .filter(|id| *id != received_from)
+            .collect();
+        targets.sort_unstable();
+        targets
+    }
+
+    /// Returns the smallest remaining capacity across the bounded neighbor channels, so that
+    /// the controller can throttle the traffic before the drone starts failing sends.
+    ///
+    /// # Returns
+    /// The minimum number of packets that can still be queued on a bounded neighbor channel,
+    /// or `None` if no neighbor channel is bounded.
+    pub fn min_neighbor_headroom(&self) -> Option<usize> {
+        self.packet_senders
+            .values()
+            .filter_map(|sender| {
+                sender
+                    .capacity()
+                    .map(|capacity| capacity.saturating_sub(sender.len()))
+            })
+            .min()
+    }
+
+    /// Returns whether the drone received the `Crash` command. A crashed drone keeps draining
+    /// its packet channel, NACKing the fragments, until all its neighbors are removed.
+    pub fn is_crashed(&self) -> bool {
+        self.is_crashed
+    }
+
+    /// Installs a callback invoked whenever the drone generates a flood response (as a leaf or
+    /// for an already received flood), with the flood ID and the route of the generated response.
+    ///
+    /// # Parameters
+    /// - `hook`: The callback, or `None` to remove it (default).
+    pub fn set_flood_response_hook(&mut self, hook: Option<Box<FloodResponseHook>>) {
+        self.on_flood_response = hook.map(Hook);
+    }
+
+    /// Sets the maximum number of sessions the drone forwards fragments for at the same time.
+    /// Fragments of a new session beyond the cap are refused with a `Dropped` NACK and counted
+    /// in `DroneStats::sessions_refused`, while the active sessions continue.
+    ///
+    /// # Parameters
+    /// - `cap`: The maximum number of active sessions, or `None` for no limit (default).
+    pub fn set_max_active_sessions(&mut self, cap: Option<usize>) {
+        self.max_active_sessions = cap;
+        self.active_sessions.clear();
+    }
+
+    /// Sets the time after which a session without forwarded fragments is no longer active,
+    /// freeing its place under the session cap.
+    ///
+    /// # Parameters
+    /// - `timeout`: The idle timeout (default: 10 seconds).
+    pub fn set_session_idle_timeout(&mut self, timeout: Duration) {
+        self.session_idle_timeout = timeout;
+    }
+
+    /// Sets the maximum payload size of the fragments the drone forwards, modeling an MTU.
+    /// Bigger fragments are dropped with a `Dropped` NACK and counted in `DroneStats::oversized_fragments`.
+    ///
+    /// # Parameters
+    /// - `max`: The maximum payload size in bytes, or `None` for no limit (default).
+    pub fn set_max_fragment_bytes(&mut self, max: Option<usize>) {
+        self.max_fragment_bytes = max;
+    }
+
+    /// Sets the maximum number of hops of the routing headers the drone accepts, protecting it
+    /// against the cost of huge routes. Longer routes are rejected before processing: fragments
+    /// with a `Dropped` NACK, other packets silently. They are counted in `DroneStats::overlong_routes`.
+    ///
+    /// # Parameters
+    /// - `max`: The maximum number of hops (default: 256), or `None` for no limit.
+    pub fn set_max_route_hops(&mut self, max: Option<usize>) {
+        self.max_route_hops = max;
+    }
+
+    /// Enables or disables a periodic summary line in the log, with the activity of the drone
+    /// since the last summary (forwarded and dropped fragments, NACKs) and its current packet drop rate.
+    /// Must be set before `run`.
+    ///
+    /// # Parameters
+    /// - `interval`: The interval between two summaries, or `None` to disable them (default).
+    pub fn set_stats_summary_interval(&mut self, interval: Option<Duration>) {
+        self.stats_summary_interval = interval;
+        self.last_stats_summary = None;
+    }
+
+    /// Sets what `flush_pending` does with the pending packets: send them immediately, or drop
+    /// them and report them to the controller (dead letters).
+    ///
+    /// # Parameters
+    /// - `policy`: The policy (default: `PendingFlushPolicy::Send`).
+    pub fn set_pending_flush_policy(&mut self, policy: PendingFlushPolicy) {
+        self.pending_flush_policy = policy;
+    }
+
+    /// Settles every packet still pending in the drone right away, according to the pending
+    /// flush policy: the held fragments (the drone is released), the fragments waiting in the
+    /// reorder buffers or for their retry, the deferred flood requests and the packets delayed
+    /// by a minimum gap. Called when the drone terminates, after a crash or when its channels
+    /// disconnect, so that no packet is silently lost.
+    pub fn flush_pending(&mut self) {
+        // Released fragments would be buffered again while the drone is still held.
+        self.held = false;
+        if self.pending_flush_policy == PendingFlushPolicy::Send {
+            self.release_held_fragments();
+            let mut sessions: Vec<u64> = self.reorder_buffers.keys().copied().collect();
+            sessions.sort_unstable();
+            for session_id in sessions {
+                self.flush_reorder_buffer(session_id);
+            }
+            self.flush_pending_floods();
+            self.flush_pending_retries();
+            self.flush_deferred_sends();
+            return;
+        }
+        let mut pending: Vec<Packet> = self.held_fragments.drain(..).collect();
+        let mut sessions: Vec<u64> = self.reorder_buffers.keys().copied().collect();
+        sessions.sort_unstable();
+        for session_id in sessions {
+            pending.extend(self.reorder_buffers.remove(&session_id).unwrap_or_default());
+        }
+        pending.extend(self.pending_floods.drain(..).map(|(_, packet, _)| packet));
+        pending.extend(self.pending_retries.drain(..).map(|(_, packet)| packet));
+        pending.extend(
+            self.deferred_sends
+                .drain(..)
+                .map(|(_, _, _, packet)| packet),
+        );
+        for packet in pending {
+            self.dead_letter(packet);
+        }
+    }
+
+    /// Enables the strict mode, where the drone receives the packets of each neighbor on a
+    /// dedicated channel, so that it can detect packets arriving from the wrong neighbor:
+    /// a fragment whose previous hop is not the neighbor that delivered it is NACKed with
+    /// `UnexpectedRecipient`, other packets are only logged since they cannot be NACKed.
+    ///
+    /// Must be called before `run`, which moves each receiver to a forwarding thread feeding the
+    /// drone loop. The channel given to `new` keeps working for the other senders, and neighbors
+    /// added later with `AddSender` deliver on it too.
+    ///
+    /// # Parameters
+    /// - `receivers`: The receiving end of the channel of each neighbor.
+    pub fn set_neighbor_receivers(&mut self, receivers: HashMap<NodeId, Receiver<Packet>>) {
+        self.neighbor_receivers = receivers;
+    }
+
+    /// Installs a callback invoked when the drone loses its last neighbor, in addition to the
+    /// warning logged and the `TelemetryEvent::Isolated` emitted.
+    ///
+    /// # Parameters
+    /// - `hook`: The callback, or `None` to remove it (default).
+    pub fn set_isolation_hook(&mut self, hook: Option<Box<IsolationHook>>) {
+        self.on_isolated = hook.map(Hook);
+    }
+
+    /// Installs a callback invoked with the old and the new packet drop rate when a
+    /// `SetPacketDropRate` command changes it. A command setting the current rate again does
+    /// not invoke it.
+    ///
+    /// # Parameters
+    /// - `hook`: The callback, or `None` to remove it (default).
+    pub fn set_pdr_change_hook(&mut self, hook: Option<Box<PdrChangeHook>>) {
+        self.on_pdr_change = hook.map(Hook);
+    }
+
+    /// Installs a resolver mapping the destination of a packet to one of the neighbors of the
+    /// drone, for routes mixing source routing with destination-based forwarding.
+    ///
+    /// When the next hop of a packet is not a neighbor, the resolver is consulted with the
+    /// destination (the last hop of the route): if it returns a neighbor, that neighbor is inserted
+    /// in the routing header as the next hop, like a default route. The resolver takes precedence
+    /// over the default route, which is used if it returns `None` or a node that is not a neighbor.
+    ///
+    /// # Parameters
+    /// - `resolver`: The resolver, or `None` for strict source routing (default).
+    pub fn set_next_hop_resolver(&mut self, resolver: Option<Box<NextHopResolver>>) {
+        self.next_hop_resolver = resolver.map(Hook);
+    }
+
+    /// Sets the channel on which the drone receives GetDroned-specific commands,
+    /// in addition to the standard `DroneCommand`s.
+    ///
+    /// # Parameters
+    /// - `receiver`: The receiving end of the extension command channel.
+    pub fn set_extension_command_receiver(&mut self, receiver: Receiver<GetDronedCommand>) {
+        self.extension_command_receiver = receiver;
+    }
+
+    /// Sets a maximum random delay before the first tick of the periodic work of the drone
+    /// (e.g. stats summaries), drawn from the drone's seeded RNG when `run` starts.
+    /// Drones started at the same time with the same intervals then do not run their periodic
+    /// work in lockstep. Packets and commands are processed immediately regardless.
+    ///
+    /// # Parameters
+    /// - `max`: The maximum startup delay, or `None` for no delay (default).
+    pub fn set_startup_jitter(&mut self, max: Option<Duration>) {
+        self.startup_jitter = max;
+    }
+
+    /// Sets the maximum number of outcomes kept in the outcome history.
+    /// When the history is full, the oldest outcome is overwritten by the new one.
+    ///
+    /// # Parameters
+    /// - `capacity`: The size of the history, 0 to disable it (default).
+    pub fn set_outcome_history_capacity(&mut self, capacity: usize) {
+        self.outcome_history_capacity = capacity;
+        while self.recent_outcomes.len() > capacity {
+            self.recent_outcomes.pop_front();
+        }
+    }
+
+    /// Returns the outcomes of the last packets processed by the drone, oldest first.
+    pub fn recent_outcomes(&self) -> &VecDeque<ForwardOutcome> {
+        &self.recent_outcomes
+    }
+
+    /// Returns how the drone currently treats the link to a node.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the node.
+    ///
+    /// # Returns
+    /// - `NeighborStatus::Unknown` if the node is not a neighbor,
+    /// - `NeighborStatus::KnownCrashed` if the last send to it failed,
+    /// - `NeighborStatus::Alive` otherwise.
+    pub fn neighbor_status(&self, id: NodeId) -> NeighborStatus {
+        if !self.packet_senders.contains_key(&id) {
+            NeighborStatus::Unknown
+        } else if self.crashed_neighbors.contains(&id) {
+            NeighborStatus::KnownCrashed
+        } else {
+            NeighborStatus::Alive
+        }
+    }
+
+    /// Enables or disables the tracing of the paths traveled by the fragments: the drone appends
+    /// its ID to the entry of each fragment it receives in the shared map.
+    /// See `PathTraceMap` for how the traces are stored.
+    ///
+    /// # Parameters
+    /// - `traces`: The map shared by the traced drones, or `None` to disable tracing (default).
+    pub fn set_path_trace_map(&mut self, traces: Option<PathTraceMap>) {
+        self.path_traces = traces;
+    }
+
+    /// Makes the logger started by `run` (with the `log` feature) give each drone its own log
+    /// files, named after its ID, see `init_logger_for_drone`. The logger is global to the process,
+    /// so the first drone starting it decides for all the drones of the process.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether each drone has its own files (off by default, keeping the shared
+    ///   timestamp-only names).
+    pub fn set_log_file_per_drone(&mut self, enabled: bool) {
+        self.log_file_per_drone = enabled;
+    }
+
+    /// Enables or disables the strict validation of flood requests: a request whose path trace
+    /// does not start with its initiator is discarded instead of only logged with a warning.
+    ///
+    /// # Parameters
+    /// - `strict`: Whether inconsistent flood requests are discarded (off by default).
+    pub fn set_strict_flood_validation(&mut self, strict: bool) {
+        self.strict_flood_validation = strict;
+    }
+
+    /// Enables or disables the reordering of commands, a fault injection for the control plane:
+    /// when a command is received, it is applied together with the commands already waiting on
+    /// the command channel, up to `max` commands, in an order drawn from the drone's seeded RNG.
+    /// A command is never delayed past the commands that were queued with it, but commands on the
+    /// same neighbor may be applied in the reverse order (e.g. a `RemoveSender` before its `AddSender`).
+    ///
+    /// # Parameters
+    /// - `max`: The maximum size of a reordered batch, or `None` to apply the commands in order (default).
+    pub fn set_command_reorder_batch(&mut self, max: Option<usize>) {
+        self.command_reorder_batch = max;
+    }
+
+    /// Enables or disables event batching: instead of sending each `DroneEvent` on the event
+    /// channel given to `new`, the drone sends them in batches on a dedicated channel, when
+    /// `max_size` events are pending, on each tick of the periodic work (every 100 ms), and
+    /// before the drone terminates. Must be set before `run`.
+    ///
+    /// # Parameters
+    /// - `sender`: The batched event channel, or `None` to send each event on its own (default).
+    /// - `max_size`: The number of pending events that triggers a batch (at least 1).
+    pub fn set_event_batching(&mut self, sender: Option<Sender<Vec<DroneEvent>>>, max_size: usize) {
+        if sender.is_none() {
+            let pending = std::mem::take(&mut self.pending_events);
+            for event in pending {
+                let _ = self.event_sender.send(event);
+            }
+        }
+        self.event_batch_sender = sender;
+        self.event_batch_size = max_size.max(1);
+    }
+
+    /// Declares the types of the neighbors of the drone. Fragments are forwarded the same way to
+    /// every neighbor, but a fragment forwarded to a client or server neighbor is also reported
+    /// with a `TelemetryEvent::DeliveredToEndpoint`. The types of undeclared neighbors are also
+    /// learned from the path traces of the flood requests they send.
+    ///
+    /// # Parameters
+    /// - `types`: The type of each neighbor; neighbors not listed are treated as drones (default: empty).
+    pub fn set_neighbor_types(&mut self, types: HashMap<NodeId, NodeType>) {
+        self.neighbor_types = types;
+    }
+
+    /// Limits the number of NACKs the drone sends per second, with a token bucket allowing bursts
+    /// of up to `rate` NACKs. Failures beyond the limit are not NACKed, and are counted in
+    /// `DroneStats::nacks_suppressed`.
+    ///
+    /// # Parameters
+    /// - `rate`: The maximum number of NACKs per second, or `None` for no limit (default).
+    pub fn set_nack_rate_limit(&mut self, rate: Option<u32>) {
+        self.nack_rate_limit = rate;
+        self.nack_tokens = rate.map_or(0.0, f64::from);
+        self.nack_tokens_refilled_at = None;
+    }
+
+    /// Returns the neighbors removed with `RemoveSender` within the retention window, sorted,
+    /// so that `ErrorInRouting` NACKs towards them can be told apart from misrouting.
+    /// A neighbor added back is no longer listed.
+    pub fn recently_removed_neighbors(&self) -> Vec<NodeId> {
+        let now = self.clock.now();
+        let mut removed: Vec<NodeId> = self
+            .recently_removed
+            .iter()
+            .filter(|(_, removed_at)| {
+                now.saturating_duration_since(**removed_at) < self.removed_neighbor_retention
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        removed.sort_unstable();
+        removed
+    }
+
+    /// Forgets the recently removed neighbors.
+    pub fn clear_recently_removed_neighbors(&mut self) {
+        self.recently_removed.clear();
+    }
+
+    /// Sets the time during which a removed neighbor is listed by `recently_removed_neighbors`.
+    ///
+    /// # Parameters
+    /// - `retention`: The retention window (default: 30 seconds).
+    pub fn set_removed_neighbor_retention(&mut self, retention: Duration) {
+        self.removed_neighbor_retention = retention;
+    }
+
+    /// Sets a tap neighbor receiving a copy of every fragment the drone forwards, like a
+    /// monitoring port. The copy keeps the session and the fragment as received by the drone,
+    /// but travels on a one-hop route from the drone to the tap, so that the tap receives it as
+    /// its destination and any node validating its routing headers accepts it. The route of the
+    /// forwarded fragment is not carried by the copy. Copies are not reported with `PacketSent` events, and are skipped while the tap is not a
+    /// neighbor or when the tap is the next hop of the fragment.
+    ///
+    /// # Parameters
+    /// - `tap`: The ID of the tap neighbor, or `None` to disable the copies (default).
+    pub fn set_tap(&mut self, tap: Option<NodeId>) {
+        self.tap = tap;
+    }
+
+    /// Applies a sequence of recorded commands, e.g. loaded with `command::load_command_file`,
+    /// as if they had been received from the controller. Must be called before `run`.
+    ///
+    /// # Parameters
+    /// - `commands`: The commands to apply, in order.
+    /// - `senders`: The channels of the neighbors added by `AddSender` commands, which cannot be
+    ///   recorded. An `AddSender` whose channel is missing is skipped with a warning.
+    pub fn replay_commands(
+        &mut self,
+        commands: &[CommandRecord],
+        senders: &HashMap<NodeId, Sender<Packet>>,
+    ) {
+        for record in commands {
+            let command = match *record {
+                CommandRecord::AddSender(id) => match senders.get(&id) {
+                    Some(sender) => DroneCommand::AddSender(id, sender.clone()),
+                    None => {
+                        warn!(
+                            "Drone {} skipped a replayed AddSender: no channel for {}",
+                            self.id, id
+                        );
+                        continue;
+                    }
+                },
+                CommandRecord::RemoveSender(id) => DroneCommand::RemoveSender(id),
+                CommandRecord::SetPacketDropRate(pdr) => DroneCommand::SetPacketDropRate(pdr),
+                CommandRecord::Crash => DroneCommand::Crash,
+            };
+            self.process_command(command);
+        }
+    }
+
+    /// Sets the thresholds above which `health` reports the drone unhealthy.
+    ///
+    /// # Parameters
+    /// - `thresholds`: The thresholds (default: a drop ratio of 0.5 and no idle limit).
+    pub fn set_health_thresholds(&mut self, thresholds: HealthThresholds) {
+        self.health_thresholds = thresholds;
+    }
+
+    /// Delays the forwarding of each flood request by a random time, drawn from the drone's
+    /// seeded RNG, to smooth the broadcast storm of a flood in dense topologies. Delayed requests
+    /// are forwarded by the periodic work, so the delay has the 100 ms resolution of its tick,
+    /// and the pending ones are forwarded at once if the drone terminates. Must be set before `run`.
+    ///
+    /// # Parameters
+    /// - `max`: The maximum delay, or `None` to forward immediately (default).
+    pub fn set_flood_jitter(&mut self, max: Option<Duration>) {
+        self.flood_jitter = max;
+    }
+
+    /// Enables or disables the first hop correction: a packet received with `hop_index` 0
+    /// pointing at another node was sent to the wrong first hop, and if that node is a neighbor
+    /// of the drone, the packet is passed on to it unchanged (with a log) instead of being NACKed
+    /// with `UnexpectedRecipient`.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether mis-addressed packets are redirected (off by default).
+    pub fn set_first_hop_correction(&mut self, enabled: bool) {
+        self.first_hop_correction = enabled;
+    }
+
+    /// Sets the number of worker threads sending the forwarded fragments, so that the drone loop
+    /// stays responsive when sends block (e.g. on bounded neighbor channels). Must be set before `run`.
+    ///
+    /// Fragments are assigned to the workers by session, so the fragments of a session keep
+    /// their order, while different sessions may overtake each other. A fragment is reported
+    /// with `PacketSent` once its worker sent it, and a failed send is NACKed. When the drone
+    /// terminates, the workers finish the fragments already handed to them before it settles
+    /// its pending packets.
+    /// The time spent blocked by the workers is not included in `total_send_block_time`.
+    /// Control packets and flood requests are always sent by the drone loop, and so are the
+    /// fragments to a neighbor with link drop rates (`set_link_drop_rates`), a minimum gap
+    /// (`set_neighbor_min_gap`) or a known crash, and every fragment while a timeline is recorded.
+    ///
+    /// # Parameters
+    /// - `count`: The number of workers, 0 to send every packet from the drone loop (default).
+    pub fn set_forward_workers(&mut self, count: usize) {
+        self.forward_worker_count = count;
+    }
+
+    /// Declares whether the neighbor channels are bounded, to pick the send behavior:
+    /// - unbounded channels (default) never fill up, so the drone uses a blocking `send`;
+    /// - bounded channels can fill up, and two drones blocked on each other's full channel would
+    ///   deadlock, so the drone uses `try_send` and retries a full channel 10 times, 1 ms apart,
+    ///   before giving up: a fragment or flood request is then NACKed like an unreachable neighbor,
+    ///   but the neighbor is not considered crashed.
+    ///
+    /// # Parameters
+    /// - `bounded`: Whether the neighbor channels are bounded.
+    pub fn set_bounded_channels(&mut self, bounded: bool) {
+        self.bounded_channels = bounded;
+    }
+
+    /// Enables or disables the deduplication of flood responses: with multi-path flooding, the
+    /// same response can come back through the drone several times, and only the first one
+    /// with a given initiator, flood ID and responder (first and last nodes of its path trace)
+    /// is forwarded. Only the last 4096 forwarded responses are remembered.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether duplicate flood responses are suppressed (off by default).
+    pub fn set_flood_response_dedup(&mut self, enabled: bool) {
+        self.flood_response_dedup = enabled;
+    }
+
+    /// Enables or disables the aggregation of flood responses next to their initiator: when the
+    /// next hop of a flood response is its initiator, only the first copy of each distinct
+    /// response (same path trace) of a flood is forwarded, damping the response storm that
+    /// converges on the initiator. Unlike `set_flood_response_dedup`, responses from the same
+    /// responder through different paths are kept. Only the last 256 floods are tracked.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether repeated responses to an adjacent initiator are suppressed (off by default).
+    pub fn set_initiator_response_dedup(&mut self, enabled: bool) {
+        self.initiator_response_dedup = enabled;
+    }
+
+    /// Enables or disables the path trace check of flood requests: a path trace only grows as a
+    /// flood propagates, so a request arriving from a neighbor with a shorter trace than a
+    /// previous copy of the same flood from the same neighbor was replayed or corrupted. It is
+    /// logged, and discarded if strict flood validation is enabled. Copies from different
+    /// neighbors are not compared, since they legitimately travel paths of different lengths.
+    /// The check remembers a trace length per flood and neighbor, for the last 4096 of them,
+    /// hence it is off by default.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether the path traces are checked.
+    pub fn set_flood_trace_check(&mut self, enabled: bool) {
+        self.flood_trace_check = enabled;
+    }
+
+    /// Sets the maximum number of emitted events kept in the event log.
+    /// When the log is full, the oldest event is overwritten by the new one.
+    ///
+    /// # Parameters
+    /// - `capacity`: The size of the log, 0 to disable it (default).
+    pub fn set_event_log_capacity(&mut self, capacity: usize) {
+        self.event_log_capacity = capacity;
+        while self.event_log.len() > capacity {
+            self.event_log.pop_front();
+        }
+    }
+
+    /// Returns the last events emitted by the drone, oldest first, whether or not the
+    /// controller received them.
+    pub fn recent_events(&self) -> Vec<DroneEvent> {
+        self.event_log.iter().cloned().collect()
+    }
+
+    /// Sets the model deciding which fragments the drone drops, restarting the cycle of a
+    /// `DropModel::BurstCycle` at its burst.
+    ///
+    /// # Parameters
+    /// - `model`: The drop model (default: `DropModel::Bernoulli`, using the packet drop rate).
+    pub fn set_drop_model(&mut self, model: DropModel) {
+        self.drop_model = model;
+        self.burst_position = 0;
+    }
+
+    /// Enables or disables the attempt events: every send to a neighbor is preceded by a
+    /// `TelemetryEvent::SendAttempted` and followed by a `TelemetryEvent::SendCompleted` telling
+    /// whether it succeeded, whereas `DroneEvent::PacketSent` is only emitted on success.
+    /// Fragments sent by the forwarding workers are not reported.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether the attempt events are emitted (default: `false`).
+    pub fn set_send_attempt_events(&mut self, enabled: bool) {
+        self.send_attempt_events = enabled;
+    }
+
+    /// Sets the directional drop rates of the link to a neighbor, to model asymmetric links.
+    ///
+    /// A packet lost on the link is sent as usual from the point of view of the drone (a
+    /// `PacketSent` event is emitted) but never reaches the neighbor, and is counted in
+    /// `DroneStats::link_losses`. These losses come on top of the packet drop rate, which only
+    /// applies to fragments received by the drone.
+    ///
+    /// With forwarding workers (`set_forward_workers`), the fragments to this neighbor are still
+    /// sent by the drone loop, so that the link drop rates apply to them.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The ID of the neighbor.
+    /// - `rates`: The drop rates of the link, or `None` for a lossless link (default).
+    pub fn set_link_drop_rates(&mut self, neighbor: NodeId, rates: Option<LinkDropRates>) {
+        match rates {
+            Some(rates) => self.link_drop_rates.insert(neighbor, rates),
+            None => self.link_drop_rates.remove(&neighbor),
+        };
+    }
+
+    /// Sets the minimum interval between two packets sent to a neighbor, to model a neighbor
+    /// that cannot accept packets faster. A packet sent too early is delayed, not dropped: it is
+    /// queued and sent on the first tick of the drone at which it is due, in order.
+    ///
+    /// A delayed packet counts as sent (`PacketSent` is emitted right away). If the neighbor is
+    /// unreachable when it is finally sent, the failure is only logged. With forwarding workers
+    /// (`set_forward_workers`), the fragments to this neighbor are still sent by the drone loop,
+    /// so that the gap applies to them.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The ID of the neighbor.
+    /// - `gap`: The minimum interval, or `None` for no gap (default).
+    ///
+    /// # Notes
+    /// The delayed packets are sent by the periodic tick of the drone, every 100 ms: gaps are
+    /// enforced as a minimum, with that granularity. Set the gaps before `run` so that the tick is enabled.
+    pub fn set_neighbor_min_gap(&mut self, neighbor: NodeId, gap: Option<Duration>) {
+        match gap {
+            Some(gap) => {
+                self.neighbor_min_gaps.insert(neighbor, gap);
+            }
+            None => {
+                self.neighbor_min_gaps.remove(&neighbor);
+                self.next_send_slots.remove(&neighbor);
+            }
+        }
+    }
+
+    /// Sets the delay of the drop retries, modeling a link-layer retransmission: a fragment drawn
+    /// as dropped is not NACKed right away but kept, and the drop is drawn again once the delay
+    /// has elapsed. If it is dropped again, it is NACKed as usual; otherwise it is forwarded.
+    ///
+    /// # Parameters
+    /// - `delay`: The delay before the retry, or `None` to NACK dropped fragments immediately (default).
+    ///
+    /// # Notes
+    /// The retries are run by the periodic tick of the drone, every 100 ms. Set the delay before
+    /// `run` so that the tick is enabled. The fragments waiting for their retry when the drone
+    /// crashes are NACKed.
+    pub fn set_drop_retry_delay(&mut self, delay: Option<Duration>) {
+        self.drop_retry_delay = delay;
+    }
+
+    /// Sets what the drone does with an ACK, a NACK or a flood response whose routing header is
+    /// invalid: send it to the controller, or log and discard it.
+    ///
+    /// # Parameters
+    /// - `policy`: The policy (default: `InvalidControlPolicy::Shortcut`).
+    pub fn set_invalid_control_policy(&mut self, policy: InvalidControlPolicy) {
+        self.invalid_control_policy = policy;
+    }
+
+    /// Sets the threshold of the high drop rate warning: the fragments dropped and forwarded are
+    /// counted over consecutive windows, and a warning is logged when the share of dropped
+    /// fragments in the current window exceeds the threshold, at most once per window. Windows
+    /// with fewer than 10 fragments are not checked.
+    ///
+    /// # Parameters
+    /// - `threshold`: The drop ratio (0.00 to 1.00) above which the warning is logged, or `None`
+    ///   to disable the warning (default).
+    /// - `window`: The duration of each observation window.
+    pub fn set_drop_rate_warning(&mut self, threshold: Option<f64>, window: Duration) {
+        self.drop_rate_warning = threshold.map(|threshold| (threshold, window));
+        self.drop_rate_window = None;
+        self.drop_rate_warned = false;
+    }
+
+    /// Enables or disables the skipping of the neighbors already in the path trace of a flood
+    /// request: the request is forwarded to every neighbor except the sender and the nodes it
+    /// already traveled through, which received it already. The protocol only requires
+    /// excluding the sender, hence this is off by default.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether the neighbors in the path trace are skipped.
+    pub fn set_skip_traced_flood_neighbors(&mut self, enabled: bool) {
+        self.skip_traced_flood_neighbors = enabled;
+    }
+
+    /// Holds or releases the drone, modeling a temporarily congested node that recovers without
+    /// loss. While held, the received fragments are buffered instead of being forwarded, up to the
+    /// hold capacity; beyond it they are dropped and counted in `DroneStats::hold_overflows`.
+    /// Commands and control packets are processed as usual, and flood requests according to the
+    /// held flood policy (see `set_held_flood_policy`). On release, the buffered fragments
+    /// are processed in arrival order, against the neighbors of the drone at that time.
+    ///
+    /// # Parameters
+    /// - `held`: Whether the drone is held (default: `false`).
+    ///
+    /// # Notes
+    /// A running drone is held and released with `GetDronedCommand::Hold`. The fragments still
+    /// buffered when the drone crashes are NACKed, and those still buffered when it terminates
+    /// are settled by `flush_pending`.
+    pub fn set_held(&mut self, held: bool) {
+        self.held = held;
+        if !held {
+            self.release_held_fragments();
+        }
+    }
+
+    /// Sets what the drone does with the flood requests it receives while it is held: process
+    /// them as usual, respond to them as a leaf, ignore them, or NACK them with `Dropped`.
+    ///
+    /// # Parameters
+    /// - `policy`: The policy (default: `HeldFloodPolicy::Forward`).
+    pub fn set_held_flood_policy(&mut self, policy: HeldFloodPolicy) {
+        self.held_flood_policy = policy;
+    }
+
+    /// Sets the maximum number of fragments buffered while the drone is held.
+    ///
+    /// # Parameters
+    /// - `capacity`: The maximum number of buffered fragments (default: 256).
+    pub fn set_hold_capacity(&mut self, capacity: usize) {
+        self.hold_capacity = capacity;
+    }
+
+    /// Sets the order in which the drone forwards the fragments of a session, to test the
+    /// reassembly of the destinations against reversed or shuffled sessions.
+    ///
+    /// # Parameters
+    /// - `order`: The fragment order (default: `FragmentOrder::InOrder`).
+    ///
+    /// # Notes
+    /// A batch is also forwarded as soon as the last fragment of its session is buffered, but a
+    /// session whose last fragment is lost before the drone stays buffered until the drone
+    /// crashes, or the order is changed. Fragments retried after a drop are not reordered.
+    pub fn set_fragment_order(&mut self, order: FragmentOrder) {
+        self.fragment_order = order;
+        if order == FragmentOrder::InOrder {
+            let mut sessions: Vec<u64> = self.reorder_buffers.keys().copied().collect();
+            sessions.sort_unstable();
+            for session_id in sessions {
+                self.flush_reorder_buffer(session_id);
+            }
+        }
+    }
+
+    /// Sets what the drone does with the packets of the session 0, which some rulesets reserve:
+    /// accept them, flag them (log and count them in `DroneStats::reserved_sessions`), or
+    /// reject them (flag them, then NACK fragments with `Dropped` and discard other packets).
+    ///
+    /// # Parameters
+    /// - `policy`: The policy (default: `ReservedSessionPolicy::Accept`).
+    pub fn set_reserved_session_policy(&mut self, policy: ReservedSessionPolicy) {
+        self.reserved_session_policy = policy;
+    }
+
+    /// Enables or disables NACK aggregation.
+    ///
+    /// A NACK carries a single fragment index, so NACKs cannot be merged into one packet:
+    /// when aggregation is enabled, only the first NACK of a session sent back on a given
+    /// route within `window` is forwarded, and the following ones are suppressed.
+    ///
+    /// # Parameters
+    /// - `window`: The aggregation window, or `None` to send one NACK per failure (default).
+    pub fn set_nack_aggregation_window(&mut self, window: Option<Duration>) {
+        self.nack_aggregation_window = window;
+        self.recent_nacks.clear();
+    }
+}
+
+// * Internal functions: the public API is the `Drone` trait and the `impl` block above
+impl GetDroned {
+    /// Marks floods as already received, used by `GetDronedBuilder` to pre-seed them.
+    ///
+    /// # Parameters
+    /// - `floods`: The `(initiator_id, flood_id)` keys of the floods.
+    pub(crate) fn seed_received_floods(&mut self, floods: HashSet<(NodeId, u64)>) {
+        self.received_floods.extend(floods);
+    }
+
+    /// Marks neighbors as known crashed, used by `GetDronedBuilder` to pre-seed them.
+    ///
+    /// # Parameters
+    /// - `neighbors`: The IDs of the neighbors.
+    pub(crate) fn seed_crashed_neighbors(&mut self, neighbors: HashSet<NodeId>) {
+        self.crashed_neighbors.extend(neighbors);
+    }
+
+    /// Adds a neighboring sender to the drone's list of known neighbors.
+    ///
+    /// # Parameters
+    /// - `id`: The unique ID of the neighboring node.
+    /// - `sender`: The communication channel (`Sender<Packet>`) to send packets to the neighbor.
+    fn add_neighbor_sender(&mut self, id: u8, sender: Sender<Packet>) {
+        self.packet_senders.insert(id, sender);
+        self.crashed_neighbors.remove(&id);
+        self.recently_removed.remove(&id);
+    }
+
+    /// Removes a neighboring sender from the drone's list of known neighbors.
+    /// If it was the last neighbor, notifies that the drone is isolated.
+    ///
+    /// # Parameters
+    /// - `id`: The unique ID of the neighboring node to be removed.
+    fn remove_neighbor_sender(&mut self, id: NodeId) {
+        self.crashed_neighbors.remove(&id);
+        let removed = self.packet_senders.remove(&id).is_some();
+        if removed {
+            let now = self.clock.now();
+            let retention = self.removed_neighbor_retention;
+            self.recently_removed
+                .retain(|_, removed_at| now.saturating_duration_since(*removed_at) < retention);
+            self.recently_removed.insert(id, now);
+        }
+        if removed && self.packet_senders.is_empty() {
+            warn!(
+                "Drone {} removed its last neighbor and is isolated",
+                self.id
+            );
+            self.send_telemetry(TelemetryEvent::Isolated);
+            if let Some(hook) = &mut self.on_isolated {
+                (hook.0)(self.id);
+            }
+        }
+    }
+
+    /// Sends a packet to the next hop of its routing header.
+    ///
+    /// ### Parameters
+    /// - `p`: The packet to be sent, with its `hop_index` pointing at this drone.
+    ///
+    /// ### Notes
+    /// - `hop_index` is incremented exactly once, here, so that the next hop receives the packet
+    ///   with the index pointing at itself. `validate_route` only reads the header, so a fragment
+    ///   validated then forwarded is not incremented twice.
+    /// - If the packet cannot be sent, fragments and flood requests are NACKed and the other
+    ///   packets are sent to the controller.
+    /// - In debug builds, panics if the outgoing header does not point at a neighbor, which
+    ///   would be a bug of the forwarding logic.
+    ///
+    /// ### Returns
+    /// The `ForwardOutcome` of the send.
+    fn send_packet(&mut self, mut p: Packet) -> ForwardOutcome {
+        let original_packet = p.clone();
+        if let Some(next_hop) = p.routing_header.next_hop() {
+            p.routing_header.hop_index += 1;
+            let sender = match self.packet_senders.get(&next_hop) {
+                Some(sender) => Some(sender.clone()),
+                None => self
+                    .route_through_resolver(&mut p)
+                    .or_else(|| self.route_through_gateway(&mut p)),
+            };
+            let Some(sender) = sender else {
+                return ForwardOutcome::Discarded;
+            };
+            debug_assert!(
+                p.routing_header.hop_index < p.routing_header.hops.len(),
+                "Drone {} forwards a packet with hop_index {} out of its route {:?}",
+                self.id,
+                p.routing_header.hop_index,
+                p.routing_header.hops
+            );
+            // The actual neighbor, which is the gateway if the packet was rerouted.
+            let target = p.routing_header.hops[p.routing_header.hop_index];
+            debug_assert!(
+                self.packet_senders.contains_key(&target),
+                "Drone {} forwards a packet to {}, which is not a neighbor (route {:?})",
+                self.id,
+                target,
+                p.routing_header.hops
+            );
+            self.maybe_corrupt_header(&mut p);
+            if let PacketType::MsgFragment(_) = p.pack_type {
+                if let Some(outcome) =
+                    self.offload_forward(target, sender.clone(), &p, &original_packet)
+                {
+                    return outcome;
+                }
+            }
+            match self.send_to(target, &sender, p.clone()) {
+                Ok(_) => {
+                    self.sent_to.insert(target);
+                    self.send_event(DroneEvent::PacketSent(p.clone()));
+                    ForwardOutcome::Forwarded(target)
+                }
+                Err(_) => match p.clone().pack_type {
+                    // The neighbor is unreachable: the NACK reports the downstream id.
+                    PacketType::FloodRequest(_) | PacketType::MsgFragment(_) => {
+                        let nack_type = NackType::ErrorInRouting(target);
+                        self.send_nack(original_packet.clone(), nack_type);
+                        ForwardOutcome::Nacked(nack_type)
+                    }
+                    _ => {
+                        self.send_shortcut(p.clone(), ShortcutReason::SendFailed(target));
+                        ForwardOutcome::Shortcut
+                    }
+                },
+            }
+        } else {
+            self.send_shortcut(p.clone(), ShortcutReason::NoNextHop);
+            ForwardOutcome::Shortcut
+        }
+    }
+
+    /// Reroutes a packet whose next hop is not a neighbor through the neighbor returned by the
+    /// next-hop resolver for its destination, inserting it in the routing header as the next hop.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet being forwarded, with its `hop_index` already pointing at the next hop.
+    ///
+    /// # Returns
+    /// - `Some(Sender<Packet>)` with the channel of the resolved neighbor if the packet was rerouted,
+    /// - `None` if there is no resolver or it did not resolve the destination to a neighbor.
+    fn route_through_resolver(&self, packet: &mut Packet) -> Option<Sender<Packet>> {
+        let next_hop = self.resolve_next_hop(&packet.routing_header)?;
+        let sender = self.packet_senders[&next_hop].clone();
+        info!(
+            "Drone {} forwards a packet of session {} through the resolved next hop {}",
+            self.id, packet.session_id, next_hop
+        );
+        packet
+            .routing_header
+            .hops
+            .insert(packet.routing_header.hop_index, next_hop);
+        Some(sender)
+    }
+
+    /// Resolves the destination of a route to a neighbor with the next-hop resolver.
+    ///
+    /// # Parameters
+    /// - `header`: The routing header whose destination is resolved.
+    ///
+    /// # Returns
+    /// The resolved neighbor, or `None` if there is no resolver or it did not return a neighbor.
+    fn resolve_next_hop(&self, header: &SourceRoutingHeader) -> Option<NodeId> {
+        let resolver = self.next_hop_resolver.as_ref()?;
+        let destination = *header.hops.last()?;
+        (resolver.0)(destination).filter(|hop| self.packet_senders.contains_key(hop))
+    }
+
+    /// Reroutes a packet whose next hop is not a neighbor through the default route,
+    /// inserting the gateway in the routing header as the next hop.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet being forwarded, with its `hop_index` already pointing at the next hop.
+    ///
+    /// # Returns
+    /// - `Some(Sender<Packet>)` with the channel of the gateway if the packet was rerouted,
+    /// - `None` if there is no default route or the gateway is not a neighbor.
+    fn route_through_gateway(&self, packet: &mut Packet) -> Option<Sender<Packet>> {
+        let gateway = self.default_route?;
+        let sender = self.packet_senders.get(&gateway)?.clone();
+        info!(
+            "Drone {} forwards a packet through its default route {}",
+            self.id, gateway
+        );
+        packet
+            .routing_header
+            .hops
+            .insert(packet.routing_header.hop_index, gateway);
+        Some(sender)
+    }
+
+    /// Flips a random bit of one of the remaining hops of the routing header, with probability
+    /// `header_corruption_rate`, and reports it.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet being forwarded, with its `hop_index` already pointing at the next hop.
+    fn maybe_corrupt_header(&mut self, packet: &mut Packet) {
+        if self.header_corruption_rate <= 0.0
+            || self.rng.gen::<f32>() >= self.header_corruption_rate
+        {
+            return;
+        }
+        let hops = &mut packet.routing_header.hops;
+        let first = packet.routing_header.hop_index;
+        if first >= hops.len() {
+            return;
+        }
+        let hop_index = self.rng.gen_range(first..hops.len());
+        let original = hops[hop_index];
+        hops[hop_index] ^= 1 << self.rng.gen_range(0..8u8);
+        let corrupted = hops[hop_index];
+        warn!(
+            "Drone {} corrupted hop {} of a packet of session {}: {} -> {}",
+            self.id, hop_index, packet.session_id, original, corrupted
+        );
+        self.send_telemetry(TelemetryEvent::HeaderCorrupted {
+            session_id: packet.session_id,
+            hop_index,
+            original,
+            corrupted,
+        });
+    }
+
+    /// Sends a packet on a neighbor channel, or delays it if the neighbor has a minimum gap
+    /// between packets that the send would not respect.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The ID of the neighbor.
+    /// - `sender`: The channel of the neighbor.
+    /// - `packet`: The packet to be sent.
+    ///
+    /// # Returns
+    /// `Ok(())` if the packet was sent or delayed, `Err(())` if the send failed.
+    fn send_to(
+        &mut self,
+        neighbor: NodeId,
+        sender: &Sender<Packet>,
+        packet: Packet,
+    ) -> Result<(), ()> {
+        let Some(gap) = self.neighbor_min_gaps.get(&neighbor).copied() else {
+            return self.transmit(neighbor, sender, packet);
+        };
+        let now = self.clock.now();
+        let slot = self
+            .next_send_slots
+            .get(&neighbor)
+            .map_or(now, |slot| (*slot).max(now));
+        self.next_send_slots.insert(neighbor, slot + gap);
+        if slot <= now {
+            return self.transmit(neighbor, sender, packet);
+        }
+        debug!(
+            "Drone {} delays a packet of session {} to {} by {:?}",
+            self.id,
+            packet.session_id,
+            neighbor,
+            slot - now
+        );
+        self.deferred_sends
+            .push_back((slot, neighbor, sender.clone(), packet));
+        Ok(())
+    }
+
+    /// Sends the packets delayed by a minimum gap whose time has come.
+    fn send_due_deferred(&mut self) {
+        let now = self.clock.now();
+        // Slots are handed out in increasing order per neighbor, but not across neighbors.
+        let (due, pending): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut self.deferred_sends)
+            .into_iter()
+            .partition(|(due_at, _, _, _)| *due_at <= now);
+        self.deferred_sends = pending;
+        for (_, neighbor, sender, packet) in due {
+            self.transmit_deferred(neighbor, &sender, packet);
+        }
+    }
+
+    /// Sends every packet delayed by a minimum gap immediately, before the drone terminates.
+    fn flush_deferred_sends(&mut self) {
+        for (_, neighbor, sender, packet) in std::mem::take(&mut self.deferred_sends) {
+            self.transmit_deferred(neighbor, &sender, packet);
+        }
+    }
+
+    /// Sends a packet that was delayed by a minimum gap, logging the failure if any.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The ID of the neighbor.
+    /// - `sender`: The channel of the neighbor.
+    /// - `packet`: The delayed packet.
+    fn transmit_deferred(&mut self, neighbor: NodeId, sender: &Sender<Packet>, packet: Packet) {
+        let session_id = packet.session_id;
+        if self.transmit(neighbor, sender, packet).is_err() {
+            warn!(
+                "Drone {} failed to send a delayed packet of session {} to {}",
+                self.id, session_id, neighbor
+            );
+        }
+    }
+
+    /// Sends a packet on a neighbor channel, accumulating the time spent blocked on it
+    /// when send block tracking is enabled, and updating the crash status of the neighbor.
+    /// With bounded channels, a full channel is retried a few times, then the send fails
+    /// without marking the neighbor as crashed.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The ID of the neighbor.
+    /// - `sender`: The channel of the neighbor.
+    /// - `packet`: The packet to be sent.
+    fn transmit(
+        &mut self,
+        neighbor: NodeId,
+        sender: &Sender<Packet>,
+        packet: Packet,
+    ) -> Result<(), ()> {
+        let session_id = packet.session_id;
+        if self.send_attempt_events {
+            self.send_telemetry(TelemetryEvent::SendAttempted {
+                session_id,
+                neighbor,
+            });
+        }
+        if self.is_lost_on_link(neighbor, &packet) {
+            self.stats.link_losses += 1;
+            info!(
+                "Drone {} lost a packet of session {} on the link to {}",
+                self.id, session_id, neighbor
+            );
+            return Ok(());
+        }
+        let start = self.track_send_block_time.then(|| self.clock.now());
+        let result = if self.bounded_channels {
+            Self::send_with_retry(sender, packet)
+        } else {
+            sender
+                .send(packet)
+                .map_err(|SendError(_)| TrySendError::Disconnected(()))
+        };
+        if let Some(start) = start {
+            self.send_block_time += self.clock.now().saturating_duration_since(start);
+        }
+        if self.send_attempt_events {
+            self.send_telemetry(TelemetryEvent::SendCompleted {
+                session_id,
+                neighbor,
+                success: result.is_ok(),
+            });
+        }
+        match result {
+            Ok(()) => {
+                self.crashed_neighbors.remove(&neighbor);
+                #[cfg(any(test, feature = "test-utils"))]
+                self.record_timeline(TimelineEntry::Sent {
+                    to: neighbor,
+                    session_id,
+                });
+                Ok(())
+            }
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    "Drone {} gave up sending to {}: its channel is full",
+                    self.id, neighbor
+                );
+                Err(())
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.crashed_neighbors.insert(neighbor);
+                Err(())
+            }
+        }
+    }
+
+    /// Decides whether a packet sent to a neighbor is lost on the link, according to the drop
+    /// rate of the link in the direction of the packet.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The ID of the neighbor.
+    /// - `packet`: The packet being sent.
+    fn is_lost_on_link(&mut self, neighbor: NodeId, packet: &Packet) -> bool {
+        let Some(rates) = self.link_drop_rates.get(&neighbor) else {
+            return false;
+        };
+        let rate = match packet.pack_type {
+            PacketType::MsgFragment(_) | PacketType::FloodRequest(_) => rates.forward,
+            PacketType::Ack(_) | PacketType::Nack(_) | PacketType::FloodResponse(_) => {
+                rates.reverse
+            }
+        };
+        rate > 0.0 && self.rng.gen::<f32>() < rate
+    }
+
+    /// Sends a packet on a bounded channel without blocking indefinitely: a full channel is
+    /// retried up to `BOUNDED_SEND_ATTEMPTS` times, `BOUNDED_SEND_RETRY_DELAY` apart.
+    ///
+    /// # Parameters
+    /// - `sender`: The channel of the neighbor.
+    /// - `packet`: The packet to be sent.
+    ///
+    /// # Returns
+    /// The result of the last attempt, without the packet.
+    fn send_with_retry(sender: &Sender<Packet>, packet: Packet) -> Result<(), TrySendError<()>> {
+        let mut packet = packet;
+        for attempt in 1..=BOUNDED_SEND_ATTEMPTS {
+            match sender.try_send(packet) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Disconnected(_)) => return Err(TrySendError::Disconnected(())),
+                Err(TrySendError::Full(rejected)) => {
+                    packet = rejected;
+                    if attempt < BOUNDED_SEND_ATTEMPTS {
+                        thread::sleep(BOUNDED_SEND_RETRY_DELAY);
+                    }
+                }
+            }
+        }
+        Err(TrySendError::Full(()))
+    }
+
+    /// Creates and sends a NACK packet to notify the sender of an error or specific event.
+    /// Used to signal issues such as unexpected recipients, routing errors, or dropped packets.
+    ///
+    /// # Parameters
+    /// - `packet`: The original packet that caused the issue.
+    /// - `nack_type`: The type of error or event that occurred.
+    fn send_nack(&mut self, mut packet: Packet, nack_type: NackType) {
+        let nack = Nack {
+            fragment_index: packet.get_fragment_index(),
+            nack_type,
+        };
+        if let NackType::UnexpectedRecipient(_) = nack_type {
+            for i in 0..packet.routing_header.hops.len() {
+                if self
+                    .packet_senders
+                    .contains_key(&packet.routing_header.hops[i])
+                {
+                    packet.routing_header.hop_index = i + 1;
+                    break;
+                }
+            }
+        }
+        if let Some(routing_header) = Self::reverse_route(&packet.routing_header) {
+            if self.coalesce_nack(packet.session_id, &routing_header) {
+                info!(
+                    "Drone {} coalesced a NACK for session {}",
+                    self.id, packet.session_id
+                );
+                return;
+            }
+            if !self.take_nack_token() {
+                self.stats.nacks_suppressed += 1;
+                return;
+            }
+            self.stats.nacks_sent += 1;
+            #[cfg(any(test, feature = "test-utils"))]
+            self.record_timeline(TimelineEntry::Nack {
+                session_id: packet.session_id,
+                nack_type,
+            });
+            self.send_packet(Packet::new_nack(routing_header, packet.session_id, nack));
+        }
+    }
+
+    /// Takes a token from the NACK bucket, refilled according to the NACK rate limit.
+    ///
+    /// # Returns
+    /// - `true` if the NACK can be sent (always the case without a rate limit),
+    /// - `false` if the rate limit is reached and the NACK must be suppressed.
+    fn take_nack_token(&mut self) -> bool {
+        let Some(rate) = self.nack_rate_limit else {
+            return true;
+        };
+        let capacity = f64::from(rate);
+        let now = self.clock.now();
+        let elapsed = self
+            .nack_tokens_refilled_at
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.nack_tokens = (self.nack_tokens + elapsed.as_secs_f64() * capacity).min(capacity);
+        self.nack_tokens_refilled_at = Some(now);
+        if self.nack_tokens < 1.0 {
+            return false;
+        }
+        self.nack_tokens -= 1.0;
+        true
+    }
+
+    /// Computes the route back to the source from the current position in a routing header:
+    /// the hops traveled so far, up to and including the current hop, in reverse order.
+    ///
+    /// # Parameters
+    /// - `header`: The routing header of the received packet.
+    ///
+    /// # Returns
+    /// - `Some(SourceRoutingHeader)` with the reversed partial route, starting at the current hop.
+    ///   A packet at its first hop yields a single-hop route.
+    /// - `None` if `hop_index` is out of the route.
+    pub(crate) fn reverse_route(header: &SourceRoutingHeader) -> Option<SourceRoutingHeader> {
+        header
+            .sub_route(..header.hop_index + 1)
+            .map(|route| route.get_reversed())
+    }
+
+    /// Checks whether a NACK must be suppressed because another NACK of the same session was
+    /// sent back on the same route within the aggregation window.
+    ///
+    /// # Returns
+    /// - `true` if the NACK is coalesced and must not be sent,
+    /// - `false` if it must be sent (always the case when aggregation is disabled).
+    fn coalesce_nack(&mut self, session_id: u64, route: &SourceRoutingHeader) -> bool {
+        let Some(window) = self.nack_aggregation_window else {
+            return false;
+        };
+        let now = self.clock.now();
+        self.recent_nacks
+            .retain(|_, sent_at| now.saturating_duration_since(*sent_at) < window);
+        let key = (session_id, route.hops.clone());
+        if self.recent_nacks.contains_key(&key) {
+            return true;
+        }
+        self.recent_nacks.insert(key, now);
+        false
+    }
+
+    fn send_flood_request(&mut self, packet: Packet, received_from: NodeId) -> usize {
+        let mut targets = self.flood_targets(received_from);
+        if self.skip_traced_flood_neighbors {
+            if let PacketType::FloodRequest(flood_request) = &packet.pack_type {
+                targets
+                    .retain(|target| !flood_request.path_trace.iter().any(|(id, _)| id == target));
+            }
+        }
+        let mut sent = 0;
+        for target in targets {
+            let sender = self.packet_senders[&target].clone();
+            if self.send_to(target, &sender, packet.clone()).is_ok() {
+                self.sent_to.insert(target);
+                self.send_event(DroneEvent::PacketSent(packet.clone()));
+                self.stats.flood_requests_forwarded += 1;
+                sent += 1;
+            }
+        }
+        if let PacketType::FloodRequest(flood_request) = &packet.pack_type {
+            self.record_flood_fanout((flood_request.initiator_id, flood_request.flood_id), sent);
+        }
+        sent
+    }
+
+    /// Records the fanout of a forwarded flood, forgetting the oldest flood if too many are tracked.
+    ///
+    /// # Parameters
+    /// - `flood_key`: The flood, as `(initiator_id, flood_id)`.
+    /// - `fanout`: The number of neighbors the flood request was forwarded to.
+    fn record_flood_fanout(&mut self, flood_key: (NodeId, u64), fanout: usize) {
+        if !self.flood_fanouts.contains_key(&flood_key) {
+            if self.fanout_floods_order.len() == MAX_TRACKED_FLOOD_FANOUTS {
+                if let Some(oldest) = self.fanout_floods_order.pop_front() {
+                    self.flood_fanouts.remove(&oldest);
+                }
+            }
+            self.fanout_floods_order.push_back(flood_key);
+        }
+        self.flood_fanouts.insert(flood_key, fanout);
+    }
+
+    /// Validates whether the received packet is correctly addressed and ready for processing.
+    /// If the packet is invalid, sends an appropriate NACK to notify the sender.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet to validate.
+    ///
+    /// # Returns
+    /// - `true` if the packet is valid and can proceed to processing.
+    /// - `false` if the packet is invalid, and a NACK has been sent.
+    fn validate_packet(&self, packet: Packet) -> Result<(), NackType> {
+        self.validate_route(&packet.routing_header)
+    }
+
+    /// Validates a routing header against the drone's position in it and its current neighbors.
+    /// The header is only read: `hop_index` is incremented when the packet is sent.
+    ///
+    /// # Parameters
+    /// - `header`: The routing header to validate.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the drone is the current hop and the next hop is one of its neighbors.
+    /// - `Err(NackType)` with the NACK describing why the header cannot be forwarded.
+    fn validate_route(&self, header: &SourceRoutingHeader) -> Result<(), NackType> {
+        if header.hops.get(header.hop_index) != Some(&self.id) {
+            return Err(NackType::UnexpectedRecipient(self.id));
+        }
+        let next_hop_index = header.hop_index + 1;
+        if next_hop_index == header.hops.len() {
+            return Err(NackType::DestinationIsDrone);
+        }
+        let next_hop = header.hops[next_hop_index];
+        if !self.packet_senders.contains_key(&next_hop)
+            && !self.has_default_route()
+            && self.resolve_next_hop(header).is_none()
+        {
+            return Err(NackType::ErrorInRouting(next_hop));
+        }
+        Ok(())
+    }
+
+    /// Checks whether the default route is set and the gateway is currently a neighbor.
+    fn has_default_route(&self) -> bool {
+        self.default_route
+            .is_some_and(|gateway| self.packet_senders.contains_key(&gateway))
+    }
+
+    /// Processes a received packet by determining its type and delegating its handling.
+    /// First validates the packet, then handles it according to its specific type (e.g., message fragment or flood request).
+    ///
+    /// # Parameters
+    /// - `packet`: The packet to process.
+    ///
+    /// # Notes
+    /// - While the drone is crashing (draining its channel), only fragments are NACKed:
+    ///   Acks, Nacks and FloodResponses are control packets and are still forwarded,
+    ///   so that acknowledgements and discovery results are not lost with the drone.
+    fn process_packet(&mut self, packet: Packet) {
+        self.stats.packets_received += 1;
+        self.last_packet_at = self.clock.now();
+        #[cfg(feature = "tracing")]
+        let _span = self.packet_span(&packet);
+        let fragment_session =
+            matches!(packet.pack_type, PacketType::MsgFragment(_)).then_some(packet.session_id);
+        if fragment_session.is_some() {
+            self.stats.fragments_received += 1;
+            self.trace_fragment(&packet);
+        }
+        if self
+            .max_route_hops
+            .is_some_and(|max| packet.routing_header.hops.len() > max)
+        {
+            let outcome = self.reject_overlong_route(packet);
+            self.count_fragment_loss(fragment_session, outcome);
+            self.record_outcome(outcome);
+            return;
+        }
+        if packet.session_id == 0 && self.reserved_session_policy != ReservedSessionPolicy::Accept {
+            if let Some(outcome) = self.check_reserved_session(packet.clone()) {
+                self.count_fragment_loss(fragment_session, outcome);
+                self.record_outcome(outcome);
+                return;
+            }
+        }
+        self.observe_sender(&packet);
+        if !matches!(packet.pack_type, PacketType::FloodRequest(_)) {
+            self.route_lengths.record(packet.routing_header.hops.len());
+        }
+        if let Some(outcome) = self.correct_first_hop(&packet) {
+            self.count_fragment_loss(fragment_session, outcome);
+            self.record_outcome(outcome);
+            return;
+        }
+        let outcome = match packet.clone().pack_type {
+            PacketType::MsgFragment(_fragment) => match self.validate_packet(packet.clone()) {
+                Ok(()) => self.process_fragment(packet.clone()),
+                Err(nack_type) => {
+                    self.check_unreachable_destination(&packet, nack_type);
+                    self.send_nack(packet, nack_type);
+                    ForwardOutcome::Nacked(nack_type)
+                }
+            },
+            PacketType::FloodRequest(flood_request) => {
+                self.process_flood_request(packet, flood_request)
+            }
+            //Ack, Nack or FloodResponse, forwarded even while crashing
+            _ => {
+                if let PacketType::Nack(nack) = &packet.pack_type {
+                    self.check_self_referential_nack(&packet, nack);
+                }
+                match self.validate_packet(packet.clone()) {
+                    Ok(())
+                        if self.is_duplicate_flood_response(&packet)
+                            || self.is_repeated_initiator_response(&packet) =>
+                    {
+                        info!(
+                            "Drone {} suppressed a duplicate flood response of session {}",
+                            self.id, packet.session_id
+                        );
+                        ForwardOutcome::Discarded
+                    }
+                    Ok(()) => {
+                        if let PacketType::FloodResponse(_) = packet.pack_type {
+                            self.stats.flood_responses_forwarded += 1;
+                        }
+                        self.send_packet(packet.clone())
+                    }
+                    Err(nack_type) => match self.invalid_control_policy {
+                        InvalidControlPolicy::Shortcut => {
+                            self.send_shortcut(packet, ShortcutReason::InvalidRoute(nack_type));
+                            ForwardOutcome::Shortcut
+                        }
+                        InvalidControlPolicy::Discard => {
+                            warn!(
+                                "Drone {} discarded a control packet of session {} with an invalid route ({:?})",
+                                self.id, packet.session_id, nack_type
+                            );
+                            ForwardOutcome::Discarded
+                        }
+                    },
+                }
+            }
+        };
+        self.count_fragment_loss(fragment_session, outcome);
+        self.record_outcome(outcome);
+    }
+
+    /// Counts and reports a fragment refused because its next hop is its destination and is not
+    /// a neighbor, to tell an unreachable destination apart from a missing intermediate hop.
+    ///
+    /// # Parameters
+    /// - `packet`: The refused fragment.
+    /// - `nack_type`: The reason why the fragment was refused.
+    fn check_unreachable_destination(&mut self, packet: &Packet, nack_type: NackType) {
+        let NackType::ErrorInRouting(next_hop) = nack_type else {
+            return;
+        };
+        let header = &packet.routing_header;
+        if header.hop_index + 2 != header.hops.len() || header.hops.last() != Some(&next_hop) {
+            return;
+        }
+        self.stats.unreachable_destinations += 1;
+        self.send_telemetry(TelemetryEvent::DestinationUnreachable {
+            session_id: packet.session_id,
+            destination: next_hop,
+        });
+    }
+
+    /// Redirects a packet whose sender mis-addressed the first hop, if first hop correction is
+    /// enabled: a packet with `hop_index` 0 pointing at another node, which is a neighbor of the
+    /// drone, is passed on unchanged to that node.
+    ///
+    /// # Parameters
+    /// - `packet`: The received packet.
+    ///
+    /// # Returns
+    /// - `Some(ForwardOutcome)` if the packet was redirected, or could not be sent,
+    /// - `None` if the packet must be processed normally.
+    fn correct_first_hop(&mut self, packet: &Packet) -> Option<ForwardOutcome> {
+        if !self.first_hop_correction || matches!(packet.pack_type, PacketType::FloodRequest(_)) {
+            return None;
+        }
+        let header = &packet.routing_header;
+        let intended = *header.hops.first().filter(|_| header.hop_index == 0)?;
+        if intended == self.id {
+            return None;
+        }
+        let sender = self.packet_senders.get(&intended)?.clone();
+        info!(
+            "Drone {} redirects a packet of session {} to its intended first hop {}",
+            self.id, packet.session_id, intended
+        );
+        if self.send_to(intended, &sender, packet.clone()).is_err() {
+            return Some(ForwardOutcome::Discarded);
+        }
+        self.sent_to.insert(intended);
+        self.send_event(DroneEvent::PacketSent(packet.clone()));
+        Some(ForwardOutcome::Forwarded(intended))
+    }
+
+    /// Checks whether a packet is a flood response already forwarded by the drone, if flood
+    /// response deduplication is enabled, and remembers it otherwise.
+    ///
+    /// # Parameters
+    /// - `packet`: The received packet.
+    ///
+    /// # Returns
+    /// - `true` if the packet is a flood response with the same initiator, flood ID and responder
+    ///   as a response already forwarded,
+    /// - `false` otherwise (always the case when deduplication is disabled).
+    fn is_duplicate_flood_response(&mut self, packet: &Packet) -> bool {
+        if !self.flood_response_dedup {
+            return false;
+        }
+        let PacketType::FloodResponse(response) = &packet.pack_type else {
+            return false;
+        };
+        let (Some((initiator, _)), Some((responder, _))) =
+            (response.path_trace.first(), response.path_trace.last())
+        else {
+            return false;
+        };
+        let key = (*initiator, response.flood_id, *responder);
+        if self.forwarded_flood_responses.contains(&key) {
+            return true;
+        }
+        if self.forwarded_responses_order.len() == MAX_TRACKED_FLOOD_RESPONSES {
+            if let Some(oldest) = self.forwarded_responses_order.pop_front() {
+                self.forwarded_flood_responses.remove(&oldest);
+            }
+        }
+        self.forwarded_responses_order.push_back(key);
+        self.forwarded_flood_responses.insert(key);
+        false
+    }
+
+    /// Checks whether a packet is a flood response about to reach its initiator with the same
+    /// path trace as a response already forwarded to it, if the aggregation next to initiators
+    /// is enabled, and remembers it otherwise.
+    ///
+    /// # Parameters
+    /// - `packet`: The received packet.
+    ///
+    /// # Returns
+    /// - `true` if the packet repeats a response already forwarded to the adjacent initiator,
+    /// - `false` otherwise (always the case when the aggregation is disabled).
+    fn is_repeated_initiator_response(&mut self, packet: &Packet) -> bool {
+        if !self.initiator_response_dedup {
+            return false;
+        }
+        let PacketType::FloodResponse(response) = &packet.pack_type else {
+            return false;
+        };
+        let Some((initiator, _)) = response.path_trace.first() else {
+            return false;
+        };
+        if packet.routing_header.next_hop() != Some(*initiator) {
+            return false;
+        }
+        let flood_key = (*initiator, response.flood_id);
+        if !self.initiator_responses.contains_key(&flood_key) {
+            if self.initiator_floods_order.len() == MAX_TRACKED_INITIATOR_FLOODS {
+                if let Some(oldest) = self.initiator_floods_order.pop_front() {
+                    self.initiator_responses.remove(&oldest);
+                }
+            }
+            self.initiator_floods_order.push_back(flood_key);
+        }
+        let trace = response.path_trace.iter().map(|(id, _)| *id).collect();
+        !self
+            .initiator_responses
+            .entry(flood_key)
+            .or_default()
+            .insert(trace)
+    }
+
+    /// Rejects a packet whose routing header exceeds the maximum number of hops, before any
+    /// processing that scans or copies the route: fragments are NACKed with `Dropped`, whose
+    /// return route only covers the hops traveled so far, and other packets are discarded.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet with the over-long route.
+    ///
+    /// # Returns
+    /// The `ForwardOutcome` of the packet.
+    fn reject_overlong_route(&mut self, packet: Packet) -> ForwardOutcome {
+        warn!(
+            "Drone {} rejected a packet of session {} with a route of {} hops",
+            self.id,
+            packet.session_id,
+            packet.routing_header.hops.len()
+        );
+        self.stats.overlong_routes += 1;
+        if let PacketType::MsgFragment(_) = packet.pack_type {
+            self.send_nack(packet, NackType::Dropped);
+            ForwardOutcome::Nacked(NackType::Dropped)
+        } else {
+            ForwardOutcome::Discarded
+        }
+    }
+
+    /// Flags a packet of the reserved session 0 and, with `ReservedSessionPolicy::Reject`,
+    /// refuses it: fragments are NACKed with `Dropped`, other packets are discarded.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet of the session 0.
+    ///
+    /// # Returns
+    /// - `Some(ForwardOutcome)` if the packet was rejected,
+    /// - `None` if the packet must be processed normally.
+    fn check_reserved_session(&mut self, packet: Packet) -> Option<ForwardOutcome> {
+        warn!(
+            "Drone {} received a packet of the reserved session 0: {:?}",
+            self.id, packet.pack_type
+        );
+        self.stats.reserved_sessions += 1;
+        if self.reserved_session_policy != ReservedSessionPolicy::Reject {
+            return None;
+        }
+        if let PacketType::MsgFragment(_) = packet.pack_type {
+            self.send_nack(packet, NackType::Dropped);
+            Some(ForwardOutcome::Nacked(NackType::Dropped))
+        } else {
+            Some(ForwardOutcome::Discarded)
+        }
+    }
+
+    /// Counts a fragment the drone did not forward, for any reason, in the losses of its session.
+    /// At most `MAX_TRACKED_LOSS_SESSIONS` sessions are tracked: the oldest one is forgotten first.
+    ///
+    /// # Parameters
+    /// - `fragment_session`: The session of the packet if it is a fragment, `None` otherwise.
+    /// - `outcome`: The outcome of the packet.
+    fn count_fragment_loss(&mut self, fragment_session: Option<u64>, outcome: ForwardOutcome) {
+        let Some(session_id) = fragment_session else {
+            return;
+        };
+        if let ForwardOutcome::Forwarded(_)
+        | ForwardOutcome::RetryDeferred
+        | ForwardOutcome::Held
+        | ForwardOutcome::Buffered = outcome
+        {
+            return;
+        }
+        if !self.session_losses.contains_key(&session_id) {
+            if self.loss_sessions_order.len() == MAX_TRACKED_LOSS_SESSIONS {
+                if let Some(oldest) = self.loss_sessions_order.pop_front() {
+                    self.session_losses.remove(&oldest);
+                }
+            }
+            self.loss_sessions_order.push_back(session_id);
+        }
+        *self.session_losses.entry(session_id).or_default() += 1;
+    }
+
+    /// Opens and enters the span covering the processing of a packet. Its `decision` and
+    /// `next_hop` fields are filled when the outcome is recorded.
+    ///
+    /// # Parameters
+    /// - `packet`: The received packet.
+    #[cfg(feature = "tracing")]
+    fn packet_span(&self, packet: &Packet) -> tracing::span::EnteredSpan {
+        tracing::info_span!(
+            "process_packet",
+            drone_id = self.id,
+            session_id = packet.session_id,
+            fragment_index = packet.get_fragment_index(),
+            decision = tracing::field::Empty,
+            next_hop = tracing::field::Empty,
+        )
+        .entered()
+    }
+
+    /// Appends the outcome of a processed packet to the recent outcomes, evicting the oldest
+    /// one if the history is full. Does nothing if the history is disabled.
+    ///
+    /// With the `tracing` feature, the outcome is also recorded in the current span: every path
+    /// settling a packet outside of `process_packet` (held, reordered or retried fragments) opens
+    /// a span for it first, so that the span of the packet being processed is not overwritten.
+    ///
+    /// # Parameters
+    /// - `outcome`: The outcome to record.
+    fn record_outcome(&mut self, outcome: ForwardOutcome) {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            span.record("decision", tracing::field::debug(&outcome));
+            if let ForwardOutcome::Forwarded(next_hop) = outcome {
+                span.record("next_hop", next_hop);
+            }
+        }
+        #[cfg(any(test, feature = "test-utils"))]
+        self.record_timeline(TimelineEntry::Outcome(outcome));
+        if self.outcome_history_capacity == 0 {
+            return;
+        }
+        if self.recent_outcomes.len() == self.outcome_history_capacity {
+            self.recent_outcomes.pop_front();
+        }
+        self.recent_outcomes.push_back(outcome);
+    }
+
+    /// Counts and reports a received NACK that refers to this drone: a NACK the drone itself
+    /// originated (first hop of its route), or an `ErrorInRouting` naming the drone. Neither
+    /// should come back to the drone with a sound route, so it reveals a routing anomaly.
+    /// The NACK is then handled as any other: forwarded, or sent to the controller if its route is invalid.
+    ///
+    /// # Parameters
+    /// - `packet`: The received NACK packet.
+    /// - `nack`: The NACK carried by the packet.
+    fn check_self_referential_nack(&mut self, packet: &Packet, nack: &Nack) {
+        let originated = packet.routing_header.hops.first() == Some(&self.id);
+        let names_self = matches!(nack.nack_type, NackType::ErrorInRouting(id) if id == self.id);
+        if originated || names_self {
+            warn!(
+                "Drone {} received a NACK referring to itself in session {}: {:?}, route {:?}",
+                self.id, packet.session_id, nack.nack_type, packet.routing_header.hops
+            );
+            self.stats.self_referential_nacks += 1;
+        }
+    }
+
+    /// Records the node an incoming packet was received from, for the asymmetric link detection.
+    ///
+    /// # Parameters
+    /// - `packet`: The received packet.
+    fn observe_sender(&mut self, packet: &Packet) {
+        if let Some(sender) = Self::infer_sender(packet) {
+            self.received_from.insert(sender);
+        }
+    }
+
+    /// Infers the node a packet was received from: the last node of the path trace for
+    /// flood requests, the previous hop otherwise.
+    ///
+    /// # Parameters
+    /// - `packet`: The received packet.
+    fn infer_sender(packet: &Packet) -> Option<NodeId> {
+        match &packet.pack_type {
+            PacketType::FloodRequest(flood_request) => {
+                flood_request.path_trace.last().map(|(id, _)| *id)
+            }
+            _ => packet
+                .routing_header
+                .hop_index
+                .checked_sub(1)
+                .and_then(|index| packet.routing_header.hops.get(index))
+                .copied(),
+        }
+    }
+
+    /// Processes a packet delivered on the dedicated channel of a neighbor (strict mode),
+    /// checking that the packet really comes from that neighbor.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The neighbor whose channel delivered the packet.
+    /// - `packet`: The received packet.
+    fn process_packet_from(&mut self, neighbor: NodeId, packet: Packet) {
+        let expected = Self::infer_sender(&packet);
+        if expected.is_some_and(|expected| expected != neighbor) {
+            warn!(
+                "Drone {} received a packet from {} but its routing indicates {:?}",
+                self.id, neighbor, expected
+            );
+            if let PacketType::MsgFragment(_) = packet.pack_type {
+                self.stats.packets_received += 1;
+                self.stats.fragments_received += 1;
+                self.trace_fragment(&packet);
+                #[cfg(feature = "tracing")]
+                let _span = self.packet_span(&packet);
+                let nack_type = NackType::UnexpectedRecipient(self.id);
+                let session_id = packet.session_id;
+                self.send_nack(packet, nack_type);
+                self.count_fragment_loss(Some(session_id), ForwardOutcome::Nacked(nack_type));
+                self.record_outcome(ForwardOutcome::Nacked(nack_type));
+                return;
+            }
+        }
+        self.process_packet(packet);
+    }
+
+    /// Moves each per-neighbor receiver (strict mode) to a thread forwarding its packets,
+    /// tagged with the neighbor ID, to a single channel read by the drone loop.
+    /// A thread stops when its neighbor disconnects or the drone loop ends.
+    ///
+    /// # Returns
+    /// The channel of the tagged packets, which never delivers anything if strict mode is off.
+    fn spawn_neighbor_forwarders(&mut self) -> Receiver<(NodeId, Packet)> {
+        if self.neighbor_receivers.is_empty() {
+            return never();
+        }
+        let (tagged_sender, tagged_receiver) = unbounded();
+        for (neighbor, receiver) in self.neighbor_receivers.drain() {
+            let tagged_sender = tagged_sender.clone();
+            thread::spawn(move || {
+                for packet in receiver.iter() {
+                    if tagged_sender.send((neighbor, packet)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        tagged_receiver
+    }
+
+    /// Starts the forwarding workers, if enabled. Each worker sends the fragments it is handed,
+    /// in order, and reports the result of each send to the drone loop on `forward_results`.
+    /// The workers stop when their job channels are closed (see `stop_forward_workers`).
+    fn spawn_forward_workers(&mut self) {
+        if self.forward_worker_count == 0 {
+            return;
+        }
+        let (result_sender, result_receiver) = unbounded();
+        for _ in 0..self.forward_worker_count {
+            let (job_sender, job_receiver) = unbounded::<ForwardJob>();
+            let result_sender = result_sender.clone();
+            let worker = thread::spawn(move || {
+                for (target, sender, packet, original) in job_receiver.iter() {
+                    let result = match sender.send(packet.clone()) {
+                        Ok(()) => Ok(packet),
+                        Err(_) => Err(original),
+                    };
+                    if result_sender.send((target, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+            self.forward_workers.push(job_sender);
+            self.forward_worker_threads.push(worker);
+        }
+        self.forward_results = result_receiver;
+    }
+
+    /// Closes the job channels of the forwarding workers and waits for them to finish the jobs
+    /// already handed to them, then settles every result they reported, so that no failed send
+    /// goes without a NACK when the drone terminates. The fragments forwarded afterwards are sent
+    /// by the drone loop.
+    fn stop_forward_workers(&mut self) {
+        self.forward_workers.clear();
+        for worker in self.forward_worker_threads.drain(..) {
+            if worker.join().is_err() {
+                warn!("Drone {} lost a forwarding worker", self.id);
+            }
+        }
+        while let Ok(result) = self.forward_results.try_recv() {
+            self.settle_forward(result);
+        }
+        self.forward_results = never();
+    }
+
+    /// Settles the result of a forwarding job: a sent fragment is reported with `PacketSent`, and
+    /// a failed send is NACKed with `ErrorInRouting`, marking the target neighbor as crashed.
+    ///
+    /// # Parameters
+    /// - `result`: The target neighbor, with the packet sent or the packet as received.
+    fn settle_forward(&mut self, (target, result): ForwardResult) {
+        match result {
+            Ok(packet) => self.send_event(DroneEvent::PacketSent(packet)),
+            Err(original) => {
+                self.crashed_neighbors.insert(target);
+                self.send_nack(original, NackType::ErrorInRouting(target));
+            }
+        }
+    }
+
+    /// Hands a fragment to the forwarding worker of its session, if the workers are running.
+    /// The fragment is reported with `PacketSent` once the worker sent it, and NACKed if the send
+    /// fails (see `settle_forward`).
+    /// Fragments to a neighbor with link drop rates, a minimum gap or a known crash, and every
+    /// fragment while a timeline is recorded, are kept on the drone loop (see `sent_by_loop`).
+    ///
+    /// # Parameters
+    /// - `target`: The neighbor the fragment is sent to.
+    /// - `sender`: The channel of the neighbor.
+    /// - `packet`: The fragment to send.
+    /// - `original`: The fragment as received, to NACK if the send fails.
+    ///
+    /// # Returns
+    /// - `Some(ForwardOutcome::Forwarded)` if a worker took the fragment,
+    /// - `None` if the fragment must be sent by the drone loop itself.
+    fn offload_forward(
+        &mut self,
+        target: NodeId,
+        sender: Sender<Packet>,
+        packet: &Packet,
+        original: &Packet,
+    ) -> Option<ForwardOutcome> {
+        if self.forward_workers.is_empty() || self.sent_by_loop(target) {
+            return None;
+        }
+        let worker = (packet.session_id % self.forward_workers.len() as u64) as usize;
+        self.forward_workers[worker]
+            .send((target, sender, packet.clone(), original.clone()))
+            .ok()?;
+        self.sent_to.insert(target);
+        Some(ForwardOutcome::Forwarded(target))
+    }
+
+    /// Checks whether the fragments to a neighbor must be sent by the drone loop even when the
+    /// forwarding workers are running, because `send_to` applies per-link behavior to them that
+    /// the workers do not: link losses, minimum gaps, and the recovery of a crashed neighbor.
+    /// The timeline also records the sends of the drone loop only.
+    ///
+    /// # Parameters
+    /// - `target`: The neighbor the fragment is sent to.
+    fn sent_by_loop(&self, target: NodeId) -> bool {
+        #[cfg(any(test, feature = "test-utils"))]
+        if self.timeline.is_some() {
+            return true;
+        }
+        self.link_drop_rates.contains_key(&target)
+            || self.neighbor_min_gaps.contains_key(&target)
+            || self.crashed_neighbors.contains(&target)
+    }
+
+    /// Handles a message fragment by forwarding it to the next hop.
+    /// Simulates packet drop based on the drone's drop model, sending a NACK if the packet is dropped.
+    ///
+    /// # Parameters
+    /// - `packet`: The message fragment to process.
+    ///
+    /// # Notes
+    /// - A crashed drone NACKs the fragment with `ErrorInRouting(self.id)`: the failing node is the drone itself.
+    /// - Failures of a downstream node (next hop not a neighbor, or its channel disconnected)
+    ///   are NACKed with `ErrorInRouting` carrying the id of the downstream node.
+    ///
+    /// # Returns
+    /// The `ForwardOutcome` of the fragment.
+    fn process_fragment(&mut self, packet: Packet) -> ForwardOutcome {
+        if self.held && !self.is_crashed {
+            return self.hold_fragment(packet);
+        }
+        if self.is_crashed {
+            let nack_type = NackType::ErrorInRouting(self.id);
+            self.send_nack(packet.clone(), nack_type);
+            return ForwardOutcome::Nacked(nack_type);
+        }
+        if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+            if self
+                .max_fragment_bytes
+                .is_some_and(|max| fragment.length as usize > max)
+            {
+                warn!(
+                    "Drone {} dropped an oversized fragment of session {}: {} bytes",
+                    self.id, packet.session_id, fragment.length
+                );
+                self.stats.oversized_fragments += 1;
+                self.send_nack(packet, NackType::Dropped);
+                return ForwardOutcome::Nacked(NackType::Dropped);
+            }
+        }
+        if !self.admit_session(packet.session_id) {
+            warn!(
+                "Drone {} refused a fragment of session {}: too many active sessions",
+                self.id, packet.session_id
+            );
+            self.stats.sessions_refused += 1;
+            self.send_telemetry(TelemetryEvent::SessionRefused {
+                session_id: packet.session_id,
+            });
+            self.send_nack(packet, NackType::Dropped);
+            return ForwardOutcome::Nacked(NackType::Dropped);
+        }
+        if self.should_drop(&packet) {
+            if let Some(delay) = self.drop_retry_delay {
+                let due = self.clock.now() + delay;
+                self.pending_retries.push((due, packet));
+                return ForwardOutcome::RetryDeferred;
+            }
+            return self.drop_fragment(packet);
+        }
+        if self.fragment_order != FragmentOrder::InOrder {
+            return self.buffer_for_reorder(packet);
+        }
+        self.forward_fragment(packet)
+    }
+
+    /// Buffers a fragment to be forwarded in the fragment order, then forwards the buffered
+    /// fragments of its session if the batch is full or the last fragment of the session is in it.
+    ///
+    /// # Parameters
+    /// - `packet`: The fragment to forward.
+    fn buffer_for_reorder(&mut self, packet: Packet) -> ForwardOutcome {
+        let batch = match self.fragment_order {
+            FragmentOrder::InOrder => 1,
+            FragmentOrder::Reverse { batch } | FragmentOrder::Shuffled { batch } => batch,
+        };
+        let session_id = packet.session_id;
+        let buffer = self.reorder_buffers.entry(session_id).or_default();
+        buffer.push(packet);
+        let has_last = buffer.iter().any(|packet| match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => {
+                fragment.fragment_index + 1 >= fragment.total_n_fragments
+            }
+            _ => false,
+        });
+        if buffer.len() >= batch || has_last {
+            self.flush_reorder_buffer(session_id);
+        }
+        ForwardOutcome::Buffered
+    }
+
+    /// Forwards the buffered fragments of a session in the fragment order. Fragments whose route
+    /// is no longer valid, or all of them if the drone crashed, are NACKed.
+    ///
+    /// # Parameters
+    /// - `session_id`: The session whose fragments are forwarded.
+    fn flush_reorder_buffer(&mut self, session_id: u64) {
+        let Some(mut batch) = self.reorder_buffers.remove(&session_id) else {
+            return;
+        };
+        match self.fragment_order {
+            FragmentOrder::InOrder => {}
+            FragmentOrder::Reverse { .. } => batch.reverse(),
+            FragmentOrder::Shuffled { .. } => batch.shuffle(&mut self.rng),
+        }
+        for packet in batch {
+            #[cfg(feature = "tracing")]
+            let _span = self.packet_span(&packet);
+            let invalid_route = if self.is_crashed {
+                Some(NackType::ErrorInRouting(self.id))
+            } else {
+                self.validate_route(&packet.routing_header).err()
+            };
+            let outcome = match invalid_route {
+                Some(nack_type) => {
+                    self.send_nack(packet, nack_type);
+                    ForwardOutcome::Nacked(nack_type)
+                }
+                None => self.forward_fragment(packet),
+            };
+            self.count_fragment_loss(Some(session_id), outcome);
+            self.record_outcome(outcome);
+        }
+    }
+
+    /// Buffers a fragment received while the drone is held, or drops it if the buffer is full.
+    ///
+    /// # Parameters
+    /// - `packet`: The received fragment.
+    fn hold_fragment(&mut self, packet: Packet) -> ForwardOutcome {
+        if self.held_fragments.len() >= self.hold_capacity {
+            self.stats.hold_overflows += 1;
+            return self.drop_fragment(packet);
+        }
+        self.held_fragments.push_back(packet);
+        ForwardOutcome::Held
+    }
+
+    /// Processes the fragments buffered while the drone was held, in arrival order. A fragment
+    /// whose route is no longer valid is NACKed.
+    fn release_held_fragments(&mut self) {
+        while let Some(packet) = self.held_fragments.pop_front() {
+            #[cfg(feature = "tracing")]
+            let _span = self.packet_span(&packet);
+            let session_id = packet.session_id;
+            let outcome = match self.validate_route(&packet.routing_header) {
+                Ok(()) => self.process_fragment(packet),
+                Err(nack_type) => {
+                    self.send_nack(packet, nack_type);
+                    ForwardOutcome::Nacked(nack_type)
+                }
+            };
+            self.count_fragment_loss(Some(session_id), outcome);
+            self.record_outcome(outcome);
+        }
+    }
+
+    /// Drops a fragment according to the drop model: NACKs it and reports it to the controller.
+    ///
+    /// # Parameters
+    /// - `packet`: The dropped fragment.
+    fn drop_fragment(&mut self, packet: Packet) -> ForwardOutcome {
+        self.stats.fragments_dropped += 1;
+        self.observe_drop_rate(true);
+        self.send_nack(packet.clone(), NackType::Dropped);
+        self.send_event(DroneEvent::PacketDropped(packet));
+        ForwardOutcome::Dropped
+    }
+
+    /// Forwards a fragment that passed every check to its next hop.
+    ///
+    /// # Parameters
+    /// - `packet`: The fragment to forward.
+    fn forward_fragment(&mut self, packet: Packet) -> ForwardOutcome {
+        self.stats.fragments_forwarded += 1;
+        self.observe_drop_rate(false);
+        self.stats.forwarded_path_length_total += packet.routing_header.hops.len() as u64;
+        let session_id = packet.session_id;
+        let fragment_index = packet.get_fragment_index();
+        let previous_hop = packet
+            .routing_header
+            .hop_index
+            .checked_sub(1)
+            .and_then(|index| packet.routing_header.hops.get(index).copied());
+        let tap_copy = self.tap.is_some().then(|| packet.clone());
+        let outcome = self.send_packet(packet);
+        if let ForwardOutcome::Forwarded(target) = outcome {
+            if let Some(copy) = tap_copy {
+                self.send_to_tap(copy, target);
+            }
+            let transit =
+                !previous_hop.is_some_and(|hop| self.is_endpoint(hop)) && !self.is_endpoint(target);
+            if self.recent_roles.len() == ROLE_WINDOW {
+                self.recent_roles.pop_front();
+            }
+            self.recent_roles.push_back(transit);
+            if self.is_endpoint(target) {
+                self.send_telemetry(TelemetryEvent::DeliveredToEndpoint {
+                    session_id,
+                    fragment_index,
+                    endpoint: target,
+                });
+            }
+        }
+        outcome
+    }
+
+    /// Counts a dropped or forwarded fragment in the current drop ratio window, starting a new
+    /// window if the current one elapsed, and logs the high drop rate warning if the ratio of the
+    /// window exceeds the threshold and the warning was not logged in this window yet.
+    ///
+    /// # Parameters
+    /// - `dropped`: Whether the fragment was dropped.
+    fn observe_drop_rate(&mut self, dropped: bool) {
+        let Some((threshold, window)) = self.drop_rate_warning else {
+            return;
+        };
+        let now = self.clock.now();
+        let (start, mut drops, mut forwards) = match self.drop_rate_window {
+            Some((start, drops, forwards)) if now.saturating_duration_since(start) < window => {
+                (start, drops, forwards)
+            }
+            _ => {
+                self.drop_rate_warned = false;
+                (now, 0, 0)
+            }
+        };
+        if dropped {
+            drops += 1;
+        } else {
+            forwards += 1;
+        }
+        self.drop_rate_window = Some((start, drops, forwards));
+        let total = drops + forwards;
+        if self.drop_rate_warned || total < DROP_RATE_WARNING_MIN_SAMPLES {
+            return;
+        }
+        let ratio = drops as f64 / total as f64;
+        if ratio > threshold {
+            warn!(
+                "Drone {} dropped {:.0}% of its last {} fragments (threshold {:.0}%)",
+                self.id,
+                ratio * 100.0,
+                total,
+                threshold * 100.0
+            );
+            self.drop_rate_warned = true;
+        }
+    }
+
+    /// Checks whether a neighbor is known to be a client or a server.
+    ///
+    /// # Parameters
+    /// - `neighbor`: The ID of the neighbor.
+    fn is_endpoint(&self, neighbor: NodeId) -> bool {
+        self.neighbor_types
+            .get(&neighbor)
+            .is_some_and(|node_type| !matches!(node_type, NodeType::Drone))
+    }
+
+    /// Appends the drone to the path trace of a fragment, if path tracing is enabled.
+    ///
+    /// # Parameters
+    /// - `packet`: The received fragment.
+    fn trace_fragment(&self, packet: &Packet) {
+        if let Some(traces) = &self.path_traces {
+            traces
+                .lock()
+                .unwrap()
+                .entry((packet.session_id, packet.get_fragment_index()))
+                .or_default()
+                .push(self.id);
+        }
+    }
+
+    /// Sends a copy of a forwarded fragment to the tap neighbor, if it is a neighbor and was not
+    /// the target of the fragment itself.
+    ///
+    /// # Parameters
+    /// - `copy`: The fragment as received by the drone.
+    /// - `target`: The neighbor the fragment was forwarded to.
+    fn send_to_tap(&mut self, mut copy: Packet, target: NodeId) {
+        let Some(tap) = self.tap.filter(|tap| *tap != target) else {
+            return;
+        };
+        copy.routing_header = SourceRoutingHeader {
+            hop_index: 1,
+            hops: vec![self.id, tap],
+        };
+        if let Some(sender) = self.packet_senders.get(&tap).cloned() {
+            if self.send_to(tap, &sender, copy).is_err() {
+                warn!(
+                    "Drone {} failed to send a fragment copy to its tap {}",
+                    self.id, tap
+                );
+            }
+        }
+    }
+
+    /// Decides whether the fragment being processed is dropped, according to the drop model.
+    ///
+    /// # Parameters
+    /// - `packet`: The fragment being processed.
+    fn should_drop(&mut self, packet: &Packet) -> bool {
+        match self.drop_model {
+            DropModel::Bernoulli => {
+                self.packet_drop_rate > 0.0 && self.rng.gen::<f32>() < self.packet_drop_rate
+            }
+            DropModel::BurstCycle { drop, pass } => {
+                let cycle = drop.saturating_add(pass);
+                if cycle == 0 {
+                    return false;
+                }
+                let position = self.burst_position % cycle;
+                self.burst_position = (position + 1) % cycle;
+                position < drop
+            }
+            DropModel::Deterministic { seed } => {
+                let hash = stable_hash(&[
+                    packet.session_id,
+                    packet.get_fragment_index(),
+                    u64::from(self.id),
+                    seed,
+                ]);
+                // The 24 high bits of the hash give a uniform value in [0, 1).
+                let draw = (hash >> 40) as f32 / (1u64 << 24) as f32;
+                draw < self.packet_drop_rate
+            }
+        }
+    }
+
+    /// Checks whether a fragment of a session can be forwarded under the session cap,
+    /// marking the session as active if so. Sessions idle for too long are expired first.
+    ///
+    /// # Parameters
+    /// - `session_id`: The session of the fragment.
+    ///
+    /// # Returns
+    /// - `true` if the session is already active, or there is room for a new one (always the case without a cap).
+    /// - `false` if the session is new and the cap is reached.
+    fn admit_session(&mut self, session_id: u64) -> bool {
+        let Some(cap) = self.max_active_sessions else {
+            return true;
+        };
+        let now = self.clock.now();
+        let timeout = self.session_idle_timeout;
+        self.active_sessions
+            .retain(|_, last_seen| now.saturating_duration_since(*last_seen) < timeout);
+        if !self.active_sessions.contains_key(&session_id) && self.active_sessions.len() >= cap {
+            return false;
+        }
+        self.active_sessions.insert(session_id, now);
+        true
+    }
+
+    /// Processes a flood request packet by determining the appropriate action based on the flood path
+    /// and the drone's neighbors. The drone can either generate a response or forward the request.
+    ///
+    /// # Parameters
+    /// - `packet`: The flood request packet being processed.
+    /// - `flood_request`: The flood request data extracted from the packet.
+    ///
+    /// # Behavior
+    /// - If the drone is already part of the flood path (`path_trace`), or it is a leaf according
+    ///   to its `LeafFloodPolicy`, it generates a response and sends it to the sender.
+    /// - Otherwise, the drone forwards the flood request to all neighbors except the sender.
+    /// - A request whose path trace does not start with its initiator, or (with the path trace
+    ///   check) is shorter than a previous copy from the same sender, is logged, and discarded
+    ///   if strict flood validation is enabled.
+    /// - A held drone applies its `HeldFloodPolicy` first.
+    ///
+    /// # Returns
+    /// The `ForwardOutcome` of the flood request.
+    fn process_flood_request(
+        &mut self,
+        mut packet: Packet,
+        mut flood_request: FloodRequest,
+    ) -> ForwardOutcome {
+        self.stats.flood_requests_received += 1;
+        let held_policy = (self.held && !self.is_crashed).then_some(self.held_flood_policy);
+        match held_policy {
+            Some(HeldFloodPolicy::Ignore) => {
+                info!(
+                    "Drone {} is held and ignored flood {} of initiator {}",
+                    self.id, flood_request.flood_id, flood_request.initiator_id
+                );
+                return ForwardOutcome::Discarded;
+            }
+            Some(HeldFloodPolicy::Nack) => {
+                // The NACK goes back along the path trace, like a flood response.
+                let mut hops: Vec<NodeId> =
+                    flood_request.path_trace.iter().map(|(id, _)| *id).collect();
+                hops.push(self.id);
+                packet.routing_header = SourceRoutingHeader {
+                    hop_index: hops.len() - 1,
+                    hops,
+                };
+                self.send_nack(packet, NackType::Dropped);
+                return ForwardOutcome::Nacked(NackType::Dropped);
+            }
+            _ => {}
+        }
+        if let Some((first, _)) = flood_request.path_trace.first() {
+            if *first != flood_request.initiator_id {
+                warn!(
+                    "Drone {} received flood {} of initiator {} with a path trace starting at {}",
+                    self.id, flood_request.flood_id, flood_request.initiator_id, first
+                );
+                if self.strict_flood_validation {
+                    return ForwardOutcome::Discarded;
+                }
+            }
+        }
+
+        let sender_id = match flood_request.path_trace.last() {
+            Some((id, _)) => *id,
+            None => flood_request.initiator_id,
+        };
+
+        if self.flood_trace_check && self.is_flood_trace_regression(&flood_request, sender_id) {
+            warn!(
+                "Drone {} received flood {} of initiator {} from {} with a shorter path trace than before",
+                self.id, flood_request.flood_id, flood_request.initiator_id, sender_id
+            );
+            if self.strict_flood_validation {
+                return ForwardOutcome::Discarded;
+            }
+        }
+
+        if let Some((id, node_type)) = flood_request.path_trace.last() {
+            self.neighbor_types
+                .entry(*id)
+                .or_insert_with(|| node_type.clone());
+        }
+        flood_request.increment(self.id, NodeType::Drone);
+
+        let flood_key = (flood_request.initiator_id, flood_request.flood_id);
+        let already_received = self.received_floods.contains(&flood_key);
+        let held_response = held_policy == Some(HeldFloodPolicy::Respond);
+        if already_received || held_response || self.is_flood_leaf(sender_id) {
+            debug!(
+                target: FLOOD_LOG_TARGET,
+                "Drone {} responds to flood {:?} ({}): path_trace_len={}, neighbors={}",
+                self.id,
+                flood_key,
+                if already_received {
+                    "already received"
+                } else if held_response {
+                    "held"
+                } else {
+                    "leaf"
+                },
+                flood_request.path_trace.len(),
+                self.packet_senders.len()
+            );
+            self.stats.floods_responded += 1;
+            self.send_telemetry(TelemetryEvent::FloodResponseGenerated {
+                initiator_id: flood_key.0,
+                flood_id: flood_key.1,
+            });
+            let response = flood_request.generate_response(packet.session_id);
+            if let Some(hook) = &mut self.on_flood_response {
+                (hook.0)(flood_key.1, &response.routing_header);
+            }
+            if response.routing_header.hops.len() < 2 {
+                // A malformed request (e.g. an empty path trace) yields a response with nowhere to go.
+                warn!(
+                    "Drone {} generated an unroutable response to flood {:?}, sending it to the controller",
+                    self.id, flood_key
+                );
+                self.send_shortcut(response, ShortcutReason::UnroutableFloodResponse);
+                ForwardOutcome::Shortcut
+            } else {
+                self.send_packet(response);
+                ForwardOutcome::FloodResponded
+            }
+        } else {
+            debug!(
+                target: FLOOD_LOG_TARGET,
+                "Drone {} forwards flood {:?}: path_trace_len={}, neighbors={}",
+                self.id,
+                flood_key,
+                flood_request.path_trace.len(),
+                self.packet_senders.len()
+            );
+            self.received_floods.insert(flood_key);
+            packet.pack_type = PacketType::FloodRequest(flood_request);
+            if let Some(max) = self.flood_jitter {
+                let delay = Duration::from_nanos(self.rng.gen_range(0..=max.as_nanos() as u64));
+                let due = self.clock.now() + delay;
+                self.pending_floods.push((due, packet, sender_id));
+                return ForwardOutcome::FloodDeferred;
+            }
+            let sent = self.send_flood_request(packet.clone(), sender_id);
+            ForwardOutcome::FloodForwarded(sent)
+        }
+    }
+
+    /// Forwards the deferred flood requests whose jitter delay has elapsed.
+    fn send_due_floods(&mut self) {
+        let now = self.clock.now();
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_floods)
+            .into_iter()
+            .partition(|(due_at, _, _)| *due_at <= now);
+        self.pending_floods = pending;
+        for (_, packet, received_from) in due {
+            self.send_flood_request(packet, received_from);
+        }
+    }
+
+    /// Retries the fragments drawn as dropped whose retry delay has elapsed.
+    fn send_due_retries(&mut self) {
+        let now = self.clock.now();
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_retries)
+            .into_iter()
+            .partition(|(due_at, _)| *due_at <= now);
+        self.pending_retries = pending;
+        for (_, packet) in due {
+            self.retry_fragment(packet);
+        }
+    }
+
+    /// Settles every fragment waiting for its retry, before the drone terminates.
+    fn flush_pending_retries(&mut self) {
+        for (_, packet) in std::mem::take(&mut self.pending_retries) {
+            self.retry_fragment(packet);
+        }
+    }
+
+    /// Retries a fragment drawn as dropped: it is NACKed if the drone crashed or its route is
+    /// no longer valid, dropped if it is drawn as dropped again, and forwarded otherwise.
+    ///
+    /// # Parameters
+    /// - `packet`: The fragment to retry.
+    fn retry_fragment(&mut self, packet: Packet) {
+        #[cfg(feature = "tracing")]
+        let _span = self.packet_span(&packet);
+        let session_id = packet.session_id;
+        let invalid_route = if self.is_crashed {
+            Some(NackType::ErrorInRouting(self.id))
+        } else {
+            self.validate_route(&packet.routing_header).err()
+        };
+        let outcome = if let Some(nack_type) = invalid_route {
+            self.send_nack(packet, nack_type);
+            ForwardOutcome::Nacked(nack_type)
+        } else if self.should_drop(&packet) {
+            self.drop_fragment(packet)
+        } else {
+            self.forward_fragment(packet)
+        };
+        self.count_fragment_loss(Some(session_id), outcome);
+        self.record_outcome(outcome);
+    }
+
+    /// Forwards every deferred flood request immediately, before the drone terminates.
+    fn flush_pending_floods(&mut self) {
+        for (_, packet, received_from) in std::mem::take(&mut self.pending_floods) {
+            self.send_flood_request(packet, received_from);
+        }
+    }
+
+    /// Drops a pending packet flushed with the `DeadLetter` policy and reports it to the
+    /// controller. A fragment is NACKed like any dropped fragment.
+    ///
+    /// # Parameters
+    /// - `packet`: The pending packet.
+    fn dead_letter(&mut self, packet: Packet) {
+        warn!(
+            "Drone {} dropped a pending packet of session {}",
+            self.id, packet.session_id
+        );
+        if let PacketType::MsgFragment(_) = packet.pack_type {
+            #[cfg(feature = "tracing")]
+            let _span = self.packet_span(&packet);
+            let session_id = packet.session_id;
+            let outcome = self.drop_fragment(packet);
+            self.count_fragment_loss(Some(session_id), outcome);
+            self.record_outcome(outcome);
+        } else {
+            self.send_event(DroneEvent::PacketDropped(packet));
+        }
+    }
+
+    /// Compares the path trace of a flood request with the longest one received for the same
+    /// flood from the same sender, and remembers the longest.
+    ///
+    /// # Parameters
+    /// - `flood_request`: The received flood request, before the drone is added to its path trace.
+    /// - `sender_id`: The node the request was received from.
+    ///
+    /// # Returns
+    /// `true` if the path trace is shorter than a previous one: the request was replayed or corrupted.
+    fn is_flood_trace_regression(
+        &mut self,
+        flood_request: &FloodRequest,
+        sender_id: NodeId,
+    ) -> bool {
+        let key = (
+            flood_request.initiator_id,
+            flood_request.flood_id,
+            sender_id,
+        );
+        let length = flood_request.path_trace.len();
+        if let Some(longest) = self.flood_trace_lengths.get_mut(&key) {
+            if length < *longest {
+                return true;
+            }
+            *longest = length;
+            return false;
+        }
+        if self.flood_traces_order.len() == MAX_TRACKED_FLOOD_TRACES {
+            if let Some(oldest) = self.flood_traces_order.pop_front() {
+                self.flood_trace_lengths.remove(&oldest);
+            }
+        }
+        self.flood_traces_order.push_back(key);
+        self.flood_trace_lengths.insert(key, length);
+        false
+    }
+
+    /// Checks whether the drone is a leaf for a flood request, according to its `LeafFloodPolicy`.
+    ///
+    /// # Parameters
+    /// - `sender_id`: The ID of the node the flood request was received from.
+    fn is_flood_leaf(&self, sender_id: NodeId) -> bool {
+        match self.leaf_flood_policy {
+            LeafFloodPolicy::RespondIfNoOtherNeighbor => {
+                self.packet_senders.keys().all(|id| *id == sender_id)
+            }
+            LeafFloodPolicy::RespondAtDegreeOne => self.packet_senders.len() == 1,
+            LeafFloodPolicy::AlwaysForward => false,
+        }
+    }
+
+    /// Processes a command sent to the drone, modifying its state or behavior accordingly.
+    ///
+    /// # Parameters
+    /// - `command`: A `DroneCommand` enum representing the action to be executed.
+    ///
+    /// # Supported Commands
+    /// - `DroneCommand::AddSender(id, sender)`: Adds a neighboring sender to the drone's network.
+    /// - `DroneCommand::Crash`: Marks the drone as crashed, disabling its functionality.
+    /// - `DroneCommand::SetPacketDropRate(pdr)`: Sets the packet drop rate to simulate unreliable communication.
+    /// - `DroneCommand::RemoveSender(id)`: Removes a neighboring sender from the drone's network.
+    fn process_command(&mut self, command: DroneCommand) {
+        self.record_command(&command);
+        match command {
+            DroneCommand::AddSender(id, sender) => {
+                self.add_neighbor_sender(id, sender);
+            }
+            DroneCommand::Crash => {
+                self.is_crashed = true;
+            }
+            DroneCommand::SetPacketDropRate(pdr) => {
+                let old = self.packet_drop_rate;
+                self.packet_drop_rate = pdr;
+                if old != pdr {
+                    if let Some(hook) = &mut self.on_pdr_change {
+                        (hook.0)(old, pdr);
+                    }
+                }
+            }
+            DroneCommand::RemoveSender(id) => {
+                self.remove_neighbor_sender(id);
+            }
+        }
+    }
+
+    /// Drains the packets left after a crash, then terminates the drone.
+    ///
+    /// The packet channel is read in arrival order (FIFO) until every sender disconnected. The
+    /// periodic tick is stopped, and the only commands still applied are `AddSender` and
+    /// `RemoveSender`, so that the controller can end the drain of two adjacent crashed drones by
+    /// removing their senders to each other. With a seeded RNG and the same commands, the NACKs
+    /// and shortcuts of the backlog are fully reproducible.
+    /// In strict mode, the packets of each neighbor are drained in their arrival order, but
+    /// the order across neighbors is not guaranteed.
+    ///
+    /// # Parameters
+    /// - `neighbor_packets`: The channel of the packets received in strict mode.
+    fn drain_after_crash(&mut self, neighbor_packets: &mut Receiver<(NodeId, Packet)>) {
+        info!("Drone {} crashed, draining its packets", self.id);
+        while !self.packet_channel_disconnected {
+            select_biased! {
+                recv(self.command_receiver) -> command => {
+                    match command {
+                        Ok(command @ (DroneCommand::AddSender(..) | DroneCommand::RemoveSender(_))) => {
+                            info!("Drone {} received a command: {:?}", self.id, command);
+                            self.process_command(command);
+                        },
+                        Ok(command) => {
+                            warn!("Drone {} ignored a command received after a crash: {:?}", self.id, command);
+                        },
+                        Err(_) => self.command_receiver = never(),
+                    }
+                },
+                recv(self.receiver) -> packet => {
+                    match packet {
+                        Ok(packet) => {
+                            info!("Drone {} received a packet: {:?}", self.id, packet);
+                            self.process_packet(packet);
+                        },
+                        Err(_) => break,
+                    }
+                },
+                recv(neighbor_packets) -> packet => {
+                    match packet {
+                        Ok((neighbor, packet)) => {
+                            info!("Drone {} received a packet from {}: {:?}", self.id, neighbor, packet);
+                            self.process_packet_from(neighbor, packet);
+                        },
+                        // Every neighbor disconnected: stop polling the channel.
+                        Err(_) => *neighbor_packets = never(),
+                    }
+                },
+                recv(self.forward_results) -> result => {
+                    match result {
+                        Ok(result) => self.settle_forward(result),
+                        Err(_) => self.forward_results = never(),
+                    }
+                },
+            }
+        }
+        self.finish_execution();
+    }
+
+    /// Settles everything the drone still holds (fragments handed to the forwarding workers,
+    /// held and reordered fragments, deferred floods, pending retries, delayed sends and batched
+    /// events) before it terminates.
+    fn finish_execution(&mut self) {
+        self.stop_forward_workers();
+        self.flush_pending();
+        self.flush_events();
+        info!("Drone {} finished execution.", self.id);
+    }
+
+    /// Applies a command together with the commands already waiting on the command channel,
+    /// in a random order (fault injection, see `set_command_reorder_batch`).
+    ///
+    /// # Parameters
+    /// - `first`: The command just received, which starts the batch.
+    ///
+    /// # Notes
+    /// Once a `Crash` of the batch is applied, the commands after it are logged and ignored, so
+    /// that no configuration is applied to a crashing drone.
+    fn process_reordered_commands(&mut self, first: DroneCommand) {
+        let max = self.command_reorder_batch.unwrap_or(1);
+        let mut batch = vec![first];
+        while batch.len() < max {
+            match self.command_receiver.try_recv() {
+                Ok(command) => {
+                    info!("Drone {} received a command: {:?}", self.id, command);
+                    batch.push(command);
+                }
+                Err(_) => break,
+            }
+        }
+        batch.shuffle(&mut self.rng);
+        for command in batch {
+            if self.is_crashed {
+                warn!(
+                    "Drone {} ignored a command received in the same batch as a crash: {:?}",
+                    self.id, command
+                );
+                continue;
+            }
+            self.process_command(command);
+        }
+    }
+
+    /// Processes a GetDroned-specific command received on the extension command channel.
+    ///
+    /// # Parameters
+    /// - `command`: A `GetDronedCommand` enum representing the action to be executed.
+    ///
+    /// # Supported Commands
+    /// - `GetDronedCommand::ReseedRng(seed)`: Reseeds the random number generator and confirms it.
+    /// - `GetDronedCommand::ResetStats`: Reports the current stats, then resets them.
+    /// - `GetDronedCommand::DumpConfig`: Logs the full configuration and state of the drone.
+    /// - `GetDronedCommand::Hold(held)`: Holds or releases the fragments received by the drone.
+    fn process_extension_command(&mut self, command: GetDronedCommand) {
+        match command {
+            GetDronedCommand::ReseedRng(seed) => {
+                self.set_rng_seed(seed);
+                self.send_telemetry(TelemetryEvent::RngReseeded(seed));
+            }
+            GetDronedCommand::ResetStats => {
+                self.send_telemetry(TelemetryEvent::StatsReset(self.stats.clone()));
+                self.reset_stats();
+            }
+            GetDronedCommand::DumpConfig => self.log_config_dump(),
+            GetDronedCommand::Hold(held) => self.set_held(held),
+        }
+    }
+
+    /// Logs the full configuration and state of the drone at info level, in a single entry.
+    fn log_config_dump(&self) {
+        let mut neighbor_types: Vec<_> = self.neighbor_types.iter().collect();
+        neighbor_types.sort_unstable_by_key(|(id, _)| **id);
+        info!(
+            "Drone {} configuration dump:\ncrashed: {}\nneighbor types: {:?}\nconfig: {:#?}",
+            self.id,
+            self.is_crashed,
+            neighbor_types,
+            self.config()
+        );
+    }
+
+    /// Records a command in the command history, overwriting the oldest one if the history is full.
+    ///
+    /// # Parameters
+    /// - `command`: The command being processed.
+    fn record_command(&mut self, command: &DroneCommand) {
+        if self.command_history_capacity == 0 {
+            return;
+        }
+        if self.command_history.len() == self.command_history_capacity {
+            self.command_history.pop_front();
+        }
+        self.command_history
+            .push_back((self.clock.now(), CommandRecord::from(command)));
+    }
+
+    /// Draws the random delay before the first tick, up to the startup jitter.
+    fn startup_delay(&mut self) -> Duration {
+        let max = self.startup_jitter.unwrap_or_default();
+        Duration::from_nanos(self.rng.gen_range(0..=max.as_nanos() as u64))
+    }
+
+    /// Checks whether the central tick is needed by any of the enabled periodic features.
+    fn needs_tick(&self) -> bool {
+        self.stats_summary_interval.is_some()
+            || self.event_batch_sender.is_some()
+            || self.flood_jitter.is_some()
+            || !self.neighbor_min_gaps.is_empty()
+            || self.drop_retry_delay.is_some()
+    }
+
+    /// Runs the periodic work of the drone, called on each central tick.
+    fn on_tick(&mut self) {
+        self.log_stats_summary();
+        self.send_due_floods();
+        self.send_due_deferred();
+        self.send_due_retries();
+        self.flush_events();
+    }
+
+    /// Logs a summary of the activity since the last summary, if the summary interval has elapsed.
+    fn log_stats_summary(&mut self) {
+        let Some(interval) = self.stats_summary_interval else {
+            return;
+        };
+        let now = self.clock.now();
+        let Some(last) = self.last_stats_summary else {
+            self.last_stats_summary = Some(now);
+            return;
+        };
+        if now.saturating_duration_since(last) < interval {
+            return;
+        }
+        let previous = &self.last_summary_stats;
+        info!(
+            "Drone {} summary: forwarded={}, dropped={}, nacks={}, pdr={}",
+            self.id,
+            self.stats.fragments_forwarded - previous.fragments_forwarded,
+            self.stats.fragments_dropped - previous.fragments_dropped,
+            self.stats.nacks_sent - previous.nacks_sent,
+            self.packet_drop_rate
+        );
+        self.last_summary_stats = self.stats.clone();
+        self.last_stats_summary = Some(now);
+    }
+
+    /// Sends an event to Simulation Controller.
+    ///
+    /// # Parameters
+    /// - `event`: A `DroneEvent` representing the event to be sent (e.g., packet sent, dropped).
+    ///
+    /// # Behavior
+    /// - Attempts to send the event via the `event_sender` channel.
+    /// - Sends a copy tagged with the drone ID on the tagged event channel, if any.
+    /// - With event batching, queues the event instead, and sends the batch if it is full.
+    /// - If the sending operation fails (e.g., the channel is closed), logs an error message with the failure reason.
+    ///
+    /// # Notes
     /// - This method ensures that events are dispatched asynchronously, allowing the drone to continue its operations.
-    fn send_event(&self, event: DroneEvent) {
+    fn send_event(&mut self, event: DroneEvent) {
+        if self.event_log_capacity > 0 {
+            if self.event_log.len() == self.event_log_capacity {
+                self.event_log.pop_front();
+            }
+            self.event_log.push_back(event.clone());
+        }
+        #[cfg(any(test, feature = "test-utils"))]
+        self.record_timeline(match &event {
+            DroneEvent::PacketSent(packet) => TimelineEntry::PacketSentEvent(packet.session_id),
+            DroneEvent::PacketDropped(packet) => {
+                TimelineEntry::PacketDroppedEvent(packet.session_id)
+            }
+            DroneEvent::ControllerShortcut(packet) => {
+                TimelineEntry::ControllerShortcutEvent(packet.session_id)
+            }
+        });
+        if let Some(sender) = &self.tagged_event_sender {
+            if let Err(e) = sender.send((self.id, event.clone())) {
+                warn!("Drone {} failed to send a tagged event: {}", self.id, e);
+            }
+        }
+        if self.event_batch_sender.is_some() {
+            self.pending_events.push(event);
+            if self.pending_events.len() >= self.event_batch_size {
+                self.flush_events();
+            }
+            return;
+        }
         match self.event_sender.send(event) {
             Ok(_) => (),
             Err(e) => println!("Failed to send event: {}", e),
         }
     }
+
+    /// Sends the pending events as one batch on the batched event channel, if any are pending.
+    fn flush_events(&mut self) {
+        if self.pending_events.is_empty() {
+            return;
+        }
+        if let Some(sender) = &self.event_batch_sender {
+            let batch = std::mem::take(&mut self.pending_events);
+            if let Err(e) = sender.send(batch) {
+                warn!("Drone {} failed to send a batch of events: {}", self.id, e);
+            }
+        }
+    }
+
+    /// Appends an entry to the timeline, if recording is on.
+    ///
+    /// # Parameters
+    /// - `entry`: The side effect to record.
+    #[cfg(any(test, feature = "test-utils"))]
+    fn record_timeline(&mut self, entry: TimelineEntry) {
+        if let Some(timeline) = &mut self.timeline {
+            timeline.push(entry);
+        }
+    }
+
+    /// Sends a packet to the controller with a `ControllerShortcut` event, preceded by a
+    /// `TelemetryEvent::ControllerShortcut` giving the reason of the shortcut.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet to be delivered by the controller.
+    /// - `reason`: Why the drone could not forward the packet itself.
+    fn send_shortcut(&mut self, packet: Packet, reason: ShortcutReason) {
+        self.send_telemetry(TelemetryEvent::ControllerShortcut {
+            session_id: packet.session_id,
+            reason,
+        });
+        self.send_event(DroneEvent::ControllerShortcut(packet));
+    }
+
+    /// Sends a GetDroned-specific event on the telemetry channel, if one is set.
+    ///
+    /// # Parameters
+    /// - `event`: The telemetry event to be sent.
+    fn send_telemetry(&self, event: TelemetryEvent) {
+        if let Some(sender) = &self.telemetry_sender {
+            if let Err(e) = sender.send(event) {
+                warn!("Drone {} failed to send a telemetry event: {}", self.id, e);
+            }
+        }
+    }
 }
 
 impl Display for GetDroned {
@@ -398,3 +4171,6 @@ impl Display for GetDroned {
         )
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,3475 @@
+use super::*;
+use crate::clock::ManualClock;
+use crate::recording::assert_timeline;
+use crossbeam_channel::{bounded, unbounded};
+use log::{Level, Record};
+use std::sync::{Mutex, Once};
+use std::{fs, thread};
+use wg_2024::network::SourceRoutingHeader;
+use wg_2024::packet::{Ack, FloodResponse, Fragment};
+
+/// Lines logged during the tests, as `(target, level, message)`.
+static LOG_LINES: Mutex<Vec<(String, Level, String)>> = Mutex::new(Vec::new());
+
+/// Logger capturing every line in `LOG_LINES`.
+struct TestLogger;
+
+impl log::Log for TestLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        LOG_LINES.lock().unwrap().push((
+            record.target().to_string(),
+            record.level(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the test logger, once per test process. It must be called before any test starts
+/// the GetDroned logger, which then finds it already installed.
+fn capture_logs() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&TestLogger).expect("the test logger is the first logger");
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+}
+
+/// Returns the captured lines logged by the drone with the given ID, as `(target, level, message)`.
+fn logged(id: NodeId) -> Vec<(String, Level, String)> {
+    let prefix = format!("Drone {id} ");
+    LOG_LINES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, _, message)| message.starts_with(&prefix))
+        .cloned()
+        .collect()
+}
+
+/// Drone under test, with the other ends of its channels kept open.
+struct TestDrone {
+    drone: GetDroned,
+    events: Receiver<DroneEvent>,
+    commands: Sender<DroneCommand>,
+    packets: Sender<Packet>,
+    neighbors: HashMap<NodeId, Receiver<Packet>>,
+}
+
+/// Creates a drone with the given neighbors, each on its own unbounded channel, and no drop.
+fn test_drone(id: NodeId, neighbors: &[NodeId]) -> TestDrone {
+    let (event_sender, events) = unbounded();
+    let (commands, command_receiver) = unbounded();
+    let (packets, packet_receiver) = unbounded();
+    let mut senders = HashMap::new();
+    let mut receivers = HashMap::new();
+    for neighbor in neighbors {
+        let (sender, receiver) = unbounded();
+        senders.insert(*neighbor, sender);
+        receivers.insert(*neighbor, receiver);
+    }
+    let drone = GetDroned::new(
+        id,
+        event_sender,
+        command_receiver,
+        packet_receiver,
+        senders,
+        0.0,
+    );
+    TestDrone {
+        drone,
+        events,
+        commands,
+        packets,
+        neighbors: receivers,
+    }
+}
+
+/// Creates a fragment of a 100-fragment session, as received by the node at `hop_index`.
+fn fragment(session_id: u64, fragment_index: u64, hops: &[NodeId], hop_index: usize) -> Packet {
+    Packet::new_fragment(
+        SourceRoutingHeader {
+            hop_index,
+            hops: hops.to_vec(),
+        },
+        session_id,
+        Fragment {
+            fragment_index,
+            total_n_fragments: 100,
+            length: 4,
+            data: [0; 128],
+        },
+    )
+}
+
+/// Creates a flood request of `initiator` with the given path trace (the initiator is a client,
+/// the other nodes are drones), along with its packet.
+fn flood(initiator: NodeId, flood_id: u64, trace: &[NodeId]) -> (Packet, FloodRequest) {
+    let request = FloodRequest {
+        flood_id,
+        initiator_id: initiator,
+        path_trace: trace
+            .iter()
+            .map(|id| {
+                let node_type = if *id == initiator {
+                    NodeType::Client
+                } else {
+                    NodeType::Drone
+                };
+                (*id, node_type)
+            })
+            .collect(),
+    };
+    let packet = Packet::new_flood_request(
+        SourceRoutingHeader {
+            hop_index: 0,
+            hops: Vec::new(),
+        },
+        0,
+        request.clone(),
+    );
+    (packet, request)
+}
+
+/// Creates a flood response of flood `flood_id` traveling along `hops`, as received by the node at
+/// `hop_index`.
+fn flood_response(flood_id: u64, hops: &[NodeId], hop_index: usize) -> Packet {
+    Packet::new_flood_response(
+        SourceRoutingHeader {
+            hop_index,
+            hops: hops.to_vec(),
+        },
+        0,
+        FloodResponse {
+            flood_id,
+            path_trace: hops.iter().map(|id| (*id, NodeType::Drone)).collect(),
+        },
+    )
+}
+
+/// Drone running on its own thread, with the other ends of its channels kept open.
+struct RunningDrone {
+    events: Receiver<DroneEvent>,
+    commands: Sender<DroneCommand>,
+    packets: Sender<Packet>,
+    neighbors: HashMap<NodeId, Receiver<Packet>>,
+    done: Receiver<()>,
+}
+
+impl RunningDrone {
+    /// Disconnects the command and packet channels of the drone, and waits for it to terminate.
+    ///
+    /// # Returns
+    /// `true` if the drone terminated within one second.
+    fn terminates(self) -> bool {
+        drop(self.commands);
+        drop(self.packets);
+        self.done.recv_timeout(Duration::from_secs(1)).is_ok()
+    }
+}
+
+/// Runs the drone on its own thread.
+fn start(test: TestDrone) -> RunningDrone {
+    let TestDrone {
+        mut drone,
+        events,
+        commands,
+        packets,
+        neighbors,
+    } = test;
+    let (done_sender, done) = unbounded();
+    thread::spawn(move || {
+        drone.run();
+        let _ = done_sender.send(());
+    });
+    RunningDrone {
+        events,
+        commands,
+        packets,
+        neighbors,
+        done,
+    }
+}
+
+/// Returns every packet waiting on a channel.
+fn take(receiver: &Receiver<Packet>) -> Vec<Packet> {
+    receiver.try_iter().collect()
+}
+
+/// Returns the NACK type and fragment index of a packet, if it is a NACK.
+fn nack_of(packet: &Packet) -> Option<(NackType, u64)> {
+    match &packet.pack_type {
+        PacketType::Nack(nack) => Some((nack.nack_type, nack.fragment_index)),
+        _ => None,
+    }
+}
+
+#[test]
+fn slow_consumer_gives_nonzero_block_time() {
+    let mut test = test_drone(1, &[0]);
+    let (slow_sender, slow_receiver) = bounded(1);
+    test.drone.packet_senders.insert(2, slow_sender.clone());
+    test.drone.set_send_block_tracking(true);
+    slow_sender.send(fragment(7, 0, &[0, 1, 2], 2)).unwrap();
+    let consumer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        slow_receiver.iter().take(2).count()
+    });
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(consumer.join().unwrap(), 2);
+    assert!(test.drone.total_send_block_time() > Duration::ZERO);
+}
+
+#[test]
+fn fast_consumer_gives_no_block_time_without_tracking() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+    assert!(matches!(
+        test.events.try_recv(),
+        Ok(DroneEvent::PacketSent(_))
+    ));
+    assert_eq!(test.drone.total_send_block_time(), Duration::ZERO);
+}
+
+#[test]
+fn nack_burst_is_coalesced_per_session() {
+    let mut test = test_drone(1, &[0]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone
+        .set_nack_aggregation_window(Some(Duration::from_millis(100)));
+    for fragment_index in 0..6 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 2], 1));
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .map(|packet| (packet.session_id, nack_of(packet)))
+        .collect();
+    assert_eq!(
+        nacks,
+        vec![
+            (7, Some((NackType::ErrorInRouting(2), 0))),
+            (8, Some((NackType::ErrorInRouting(2), 0))),
+        ]
+    );
+
+    clock.advance(Duration::from_millis(100));
+    test.drone.process_packet(fragment(7, 6, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&0]).len(), 1);
+}
+
+#[test]
+fn nacks_are_not_coalesced_without_window() {
+    let mut test = test_drone(1, &[0]);
+    for _ in 0..3 {
+        test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    }
+    assert_eq!(take(&test.neighbors[&0]).len(), 3);
+}
+
+#[test]
+fn can_route_checks_current_and_next_hop() {
+    let test = test_drone(1, &[0, 2]);
+    let header = |hops: &[NodeId], hop_index| SourceRoutingHeader {
+        hop_index,
+        hops: hops.to_vec(),
+    };
+    assert!(test.drone.can_route(&header(&[0, 1, 2], 1)));
+    assert!(!test.drone.can_route(&header(&[0, 1, 3], 1)));
+    assert!(!test.drone.can_route(&header(&[0, 5, 2], 1)));
+    assert!(!test.drone.can_route(&header(&[0, 1], 1)));
+    assert!(test.neighbors.values().all(|n| n.is_empty()));
+}
+
+#[test]
+fn unknown_next_hop_goes_through_default_route() {
+    let mut test = test_drone(1, &[0, 4]);
+    test.drone.set_default_route(Some(4));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 9], 1));
+    let forwarded = take(&test.neighbors[&4]);
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0].routing_header.hops, vec![0, 1, 4, 9]);
+    assert_eq!(forwarded[0].routing_header.hop_index, 2);
+    assert!(take(&test.neighbors[&0]).is_empty());
+}
+
+#[test]
+fn unknown_next_hop_is_nacked_without_default_route() {
+    let mut test = test_drone(1, &[0, 4]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 9], 1));
+    assert!(take(&test.neighbors[&4]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::ErrorInRouting(9), 0)]);
+}
+
+#[test]
+fn header_corruption_flips_one_bit_of_a_remaining_hop() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    test.drone.set_rng_seed(42);
+    test.drone.set_header_corruption_rate(1.0);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2, 3], 1));
+
+    let forwarded = take(&test.neighbors[&2]);
+    assert_eq!(forwarded.len(), 1);
+    let Ok(TelemetryEvent::HeaderCorrupted {
+        session_id,
+        hop_index,
+        original,
+        corrupted,
+    }) = telemetry.try_recv()
+    else {
+        panic!("expected a HeaderCorrupted event");
+    };
+    assert_eq!(session_id, 7);
+    assert!((2..4).contains(&hop_index));
+    assert_eq!(original, [0, 1, 2, 3][hop_index]);
+    assert_eq!((original ^ corrupted).count_ones(), 1);
+    assert_eq!(forwarded[0].routing_header.hops[hop_index], corrupted);
+}
+
+#[test]
+fn header_is_not_corrupted_by_default() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2, 3], 1));
+    let forwarded = take(&test.neighbors[&2]);
+    assert_eq!(forwarded[0].routing_header.hops, vec![0, 1, 2, 3]);
+    assert!(telemetry.try_recv().is_err());
+}
+
+#[test]
+fn leaf_flood_policies() {
+    // The response travels back to the initiator, a neighbor, with the drone in its path trace.
+    let assert_response = |packet: &Packet| match &packet.pack_type {
+        PacketType::FloodResponse(response) => {
+            assert_eq!(packet.routing_header.hops, vec![1, 0]);
+            assert!(matches!(
+                response.path_trace[..],
+                [(0, NodeType::Client), (1, NodeType::Drone)]
+            ));
+        }
+        _ => panic!("unexpected packet: {packet:?}"),
+    };
+    let mut test = test_drone(1, &[0]);
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodResponded
+    );
+    let sent = take(&test.neighbors[&0]);
+    assert_eq!(sent.len(), 1);
+    assert_response(&sent[0]);
+
+    let mut test = test_drone(1, &[0]);
+    test.drone
+        .set_leaf_flood_policy(LeafFloodPolicy::AlwaysForward);
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodForwarded(0)
+    );
+    assert!(take(&test.neighbors[&0]).is_empty());
+
+    let mut test = test_drone(1, &[0]);
+    test.drone
+        .set_leaf_flood_policy(LeafFloodPolicy::RespondAtDegreeOne);
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodResponded
+    );
+    let sent = take(&test.neighbors[&0]);
+    assert_eq!(sent.len(), 1);
+    assert_response(&sent[0]);
+
+    // The only neighbor is not the sender: only `RespondAtDegreeOne` short-circuits.
+    let mut test = test_drone(1, &[5]);
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodForwarded(1)
+    );
+    let mut test = test_drone(1, &[5]);
+    test.drone
+        .set_leaf_flood_policy(LeafFloodPolicy::RespondAtDegreeOne);
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodResponded
+    );
+    // The request is not forwarded to the neighbor, nor is the response, bound to the sender.
+    assert!(take(&test.neighbors[&5]).is_empty());
+}
+
+#[test]
+fn flood_decisions_are_logged_on_the_flood_target() {
+    capture_logs();
+    let mut test = test_drone(205, &[0, 2]);
+    let (packet, request) = flood(0, 1, &[0]);
+    test.drone
+        .process_flood_request(packet.clone(), request.clone());
+    test.drone.process_flood_request(packet, request);
+
+    let lines: Vec<_> = logged(205)
+        .into_iter()
+        .filter(|(target, _, _)| target == FLOOD_LOG_TARGET)
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|(_, level, _)| *level == Level::Debug));
+    assert!(lines[0].2.contains("forwards flood"));
+    assert!(lines[1].2.contains("already received"));
+}
+
+#[test]
+fn command_history_keeps_the_last_commands_with_their_time() {
+    let mut test = test_drone(1, &[]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.5));
+    assert!(test.drone.command_history().is_empty());
+
+    test.drone.set_command_history_capacity(2);
+    let start = clock.now();
+    let (sender, _receiver) = unbounded();
+    test.drone
+        .process_command(DroneCommand::AddSender(3, sender));
+    clock.advance(Duration::from_secs(1));
+    test.drone.process_command(DroneCommand::RemoveSender(3));
+    clock.advance(Duration::from_secs(1));
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.1));
+
+    let history: Vec<_> = test.drone.command_history().iter().copied().collect();
+    assert_eq!(
+        history,
+        vec![
+            (
+                start + Duration::from_secs(1),
+                CommandRecord::RemoveSender(3)
+            ),
+            (
+                start + Duration::from_secs(2),
+                CommandRecord::SetPacketDropRate(0.1)
+            ),
+        ]
+    );
+
+    test.drone.set_command_history_capacity(1);
+    assert_eq!(test.drone.command_history().len(), 1);
+}
+
+#[test]
+fn forced_crash_nacks_fragments_and_forwards_control_packets() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_crashed(true);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert!(take(&test.neighbors[&2]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::ErrorInRouting(1), 0)]);
+
+    let ack = Packet::new_ack(
+        SourceRoutingHeader {
+            hop_index: 1,
+            hops: vec![2, 1, 0],
+        },
+        7,
+        0,
+    );
+    test.drone.process_packet(ack);
+    assert!(matches!(
+        take(&test.neighbors[&0])[..],
+        [Packet {
+            pack_type: PacketType::Ack(_),
+            ..
+        }]
+    ));
+
+    test.drone.set_crashed(false);
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn generated_flood_responses_are_told_apart_from_forwarded_ones() {
+    let mut test = test_drone(1, &[0]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    let (packet, request) = flood(0, 4, &[0]);
+    test.drone.process_flood_request(packet, request);
+    assert_eq!(
+        telemetry.try_recv(),
+        Ok(TelemetryEvent::FloodResponseGenerated {
+            initiator_id: 0,
+            flood_id: 4,
+        })
+    );
+    assert_eq!(test.drone.stats().floods_responded, 1);
+
+    test.drone.process_packet(flood_response(5, &[3, 1, 0], 1));
+    assert_eq!(take(&test.neighbors[&0]).len(), 2);
+    assert!(telemetry.try_recv().is_err());
+    assert_eq!(test.drone.stats().floods_responded, 1);
+    assert_eq!(test.drone.stats().flood_responses_forwarded, 1);
+}
+
+#[test]
+fn flood_response_hook_gets_the_generated_route() {
+    let mut test = test_drone(1, &[0]);
+    let routes = Arc::new(Mutex::new(Vec::new()));
+    let hook_routes = routes.clone();
+    test.drone
+        .set_flood_response_hook(Some(Box::new(move |flood_id, header| {
+            hook_routes
+                .lock()
+                .unwrap()
+                .push((flood_id, header.hops.clone()));
+        })));
+    let (packet, request) = flood(0, 4, &[0]);
+    test.drone.process_flood_request(packet, request);
+    test.drone.process_packet(flood_response(5, &[3, 1, 0], 1));
+    assert_eq!(*routes.lock().unwrap(), vec![(4, vec![1, 0])]);
+}
+
+#[test]
+fn sessions_beyond_the_cap_are_refused_until_one_is_idle() {
+    let mut test = test_drone(1, &[0, 2]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    test.drone.set_max_active_sessions(Some(1));
+    test.drone.set_session_idle_timeout(Duration::from_secs(10));
+
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 2);
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::Dropped, 0)]);
+    assert_eq!(
+        telemetry.try_recv(),
+        Ok(TelemetryEvent::SessionRefused { session_id: 8 })
+    );
+    assert_eq!(test.drone.stats().sessions_refused, 1);
+
+    clock.advance(Duration::from_secs(10));
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn nack_names_the_failing_node() {
+    let mut test = test_drone(1, &[0, 2]);
+    // Neighbor 2 crashed: its channel is disconnected.
+    test.neighbors.remove(&2);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.set_crashed(true);
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(
+        nacks,
+        vec![
+            (NackType::ErrorInRouting(2), 0),
+            (NackType::ErrorInRouting(1), 1),
+        ]
+    );
+}
+
+#[test]
+fn config_reflects_the_settings() {
+    let mut test = test_drone(1, &[4, 2]);
+    let config = test.drone.config();
+    assert_eq!(config.id, 1);
+    assert_eq!(config.neighbors, vec![2, 4]);
+    assert_eq!(config.default_route, None);
+    assert_eq!(config.session_idle_timeout, DEFAULT_SESSION_IDLE_TIMEOUT);
+
+    test.drone.set_default_route(Some(4));
+    test.drone.set_max_active_sessions(Some(3));
+    test.drone.set_tap(Some(2));
+    test.drone.set_link_drop_rates(
+        4,
+        Some(LinkDropRates {
+            forward: 0.1,
+            reverse: 0.2,
+        }),
+    );
+    test.drone
+        .set_neighbor_min_gap(2, Some(Duration::from_millis(5)));
+    test.drone
+        .set_stats_summary_interval(Some(Duration::from_secs(60)));
+    test.drone
+        .set_startup_jitter(Some(Duration::from_millis(50)));
+    test.drone.set_outcome_history_capacity(8);
+    test.drone
+        .set_removed_neighbor_retention(Duration::from_secs(30));
+    let thresholds = HealthThresholds {
+        max_drop_ratio: 0.25,
+        max_idle: Some(Duration::from_secs(5)),
+    };
+    test.drone.set_health_thresholds(thresholds);
+    test.drone.set_event_log_capacity(16);
+    let config = test.drone.config();
+    assert_eq!(config.default_route, Some(4));
+    assert_eq!(config.max_active_sessions, Some(3));
+    assert_eq!(config.tap, Some(2));
+    assert_eq!(
+        config.link_drop_rates,
+        vec![(
+            4,
+            LinkDropRates {
+                forward: 0.1,
+                reverse: 0.2
+            }
+        )]
+    );
+    assert_eq!(
+        config.neighbor_min_gaps,
+        vec![(2, Duration::from_millis(5))]
+    );
+    assert_eq!(config.stats_summary_interval, Some(Duration::from_secs(60)));
+    assert_eq!(config.startup_jitter, Some(Duration::from_millis(50)));
+    assert_eq!(config.outcome_history_capacity, 8);
+    assert_eq!(config.removed_neighbor_retention, Duration::from_secs(30));
+    assert_eq!(config.health_thresholds, thresholds);
+    assert_eq!(config.event_log_capacity, 16);
+}
+
+#[test]
+fn profile_replaces_its_settings_at_once() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    test.drone.set_default_route(Some(2));
+    let rates = LinkDropRates {
+        forward: 0.5,
+        reverse: 0.0,
+    };
+    let profile = DroneProfile {
+        packet_drop_rate: 0.3,
+        leaf_flood_policy: LeafFloodPolicy::AlwaysForward,
+        max_active_sessions: Some(2),
+        session_idle_timeout: Duration::from_secs(1),
+        pending_flush_policy: PendingFlushPolicy::DeadLetter,
+        held_flood_policy: HeldFloodPolicy::Nack,
+        drop_model: DropModel::Deterministic { seed: 3 },
+        link_drop_rates: vec![(2, rates)],
+        invalid_control_policy: InvalidControlPolicy::Discard,
+        reserved_session_policy: ReservedSessionPolicy::Reject,
+        ..DroneProfile::default()
+    };
+    test.drone.apply_profile(profile.clone());
+
+    let config = test.drone.config();
+    assert_eq!(config.packet_drop_rate, 0.3);
+    assert_eq!(config.default_route, None);
+    assert_eq!(config.leaf_flood_policy, LeafFloodPolicy::AlwaysForward);
+    assert_eq!(config.max_active_sessions, Some(2));
+    assert_eq!(config.session_idle_timeout, Duration::from_secs(1));
+    assert_eq!(config.pending_flush_policy, PendingFlushPolicy::DeadLetter);
+    assert_eq!(config.held_flood_policy, HeldFloodPolicy::Nack);
+    assert_eq!(config.drop_model, DropModel::Deterministic { seed: 3 });
+    assert_eq!(config.link_drop_rates, vec![(2, rates)]);
+    assert_eq!(config.invalid_control_policy, InvalidControlPolicy::Discard);
+    assert_eq!(
+        config.reserved_session_policy,
+        ReservedSessionPolicy::Reject
+    );
+    assert_eq!(
+        telemetry.try_iter().collect::<Vec<_>>(),
+        vec![TelemetryEvent::ProfileApplied(profile)]
+    );
+}
+
+#[test]
+fn oversized_fragments_are_dropped() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_max_fragment_bytes(Some(3));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert!(take(&test.neighbors[&2]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::Dropped, 0)]);
+    assert_eq!(test.drone.stats().oversized_fragments, 1);
+
+    test.drone.set_max_fragment_bytes(Some(4));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn neighbors_never_heard_from_are_asymmetric() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 3], 1));
+    assert_eq!(test.drone.asymmetric_links(), vec![2, 3]);
+
+    test.drone.process_packet(fragment(9, 0, &[3, 1, 0], 1));
+    assert_eq!(test.drone.asymmetric_links(), vec![2]);
+}
+
+#[test]
+fn response_to_an_empty_path_trace_goes_to_the_controller() {
+    let mut test = test_drone(1, &[0]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    let (packet, request) = flood(0, 1, &[]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::Shortcut
+    );
+    assert!(take(&test.neighbors[&0]).is_empty());
+    assert!(matches!(
+        test.events.try_recv(),
+        Ok(DroneEvent::ControllerShortcut(Packet {
+            pack_type: PacketType::FloodResponse(_),
+            ..
+        }))
+    ));
+    assert!(telemetry.try_iter().any(|event| event
+        == TelemetryEvent::ControllerShortcut {
+            session_id: 0,
+            reason: ShortcutReason::UnroutableFloodResponse,
+        }));
+}
+
+#[test]
+fn stats_summary_is_logged_once_per_interval() {
+    capture_logs();
+    let mut test = test_drone(211, &[0, 2]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone
+        .set_stats_summary_interval(Some(Duration::from_secs(10)));
+    test.drone.log_stats_summary();
+    test.drone.process_packet(fragment(7, 0, &[0, 211, 2], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 211, 2], 1));
+    clock.advance(Duration::from_secs(5));
+    test.drone.log_stats_summary();
+    clock.advance(Duration::from_secs(5));
+    test.drone.log_stats_summary();
+    test.drone.process_packet(fragment(7, 2, &[0, 211, 2], 1));
+    clock.advance(Duration::from_secs(10));
+    test.drone.log_stats_summary();
+
+    let summaries: Vec<_> = logged(211)
+        .into_iter()
+        .filter(|(_, _, message)| message.contains("summary"))
+        .map(|(_, level, message)| (level, message))
+        .collect();
+    assert_eq!(
+        summaries,
+        vec![
+            (
+                Level::Info,
+                "Drone 211 summary: forwarded=2, dropped=0, nacks=0, pdr=0".to_string()
+            ),
+            (
+                Level::Info,
+                "Drone 211 summary: forwarded=1, dropped=0, nacks=0, pdr=0".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn fragment_from_the_wrong_neighbor_is_nacked() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone
+        .process_packet_from(2, fragment(7, 0, &[0, 1, 2], 1));
+    assert!(take(&test.neighbors[&2]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::UnexpectedRecipient(1), 0)]);
+
+    test.drone
+        .process_packet_from(0, fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn disconnected_neighbor_channels_do_not_stop_the_drone() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (neighbor_sender, neighbor_receiver) = unbounded();
+    test.drone
+        .set_neighbor_receivers(HashMap::from([(0, neighbor_receiver)]));
+    let running = start(test);
+    let to_neighbor = &running.neighbors[&2];
+    neighbor_sender.send(fragment(7, 0, &[0, 1, 2], 1)).unwrap();
+    drop(neighbor_sender);
+    let timeout = Duration::from_secs(1);
+    assert!(to_neighbor.recv_timeout(timeout).is_ok());
+
+    // The drone still processes the packets of its main channel.
+    running.packets.send(fragment(7, 1, &[0, 1, 2], 1)).unwrap();
+    assert_eq!(
+        to_neighbor
+            .recv_timeout(timeout)
+            .map(|packet| packet.get_fragment_index()),
+        Ok(1)
+    );
+    assert!((0..2).all(|_| matches!(
+        running.events.recv_timeout(timeout),
+        Ok(DroneEvent::PacketSent(_))
+    )));
+    assert!(running.terminates());
+}
+
+#[test]
+fn forwarded_route_lengths_are_summed() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone
+        .process_packet(fragment(7, 1, &[5, 0, 1, 2, 6], 2));
+    // A refused fragment is not counted.
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 3], 1));
+    assert_eq!(test.drone.stats().fragments_forwarded, 2);
+    assert_eq!(test.drone.stats().forwarded_path_length_total, 8);
+    assert_eq!(test.drone.stats().average_path_length(), 4.0);
+}
+
+#[test]
+fn isolation_is_reported_when_the_last_neighbor_is_removed() {
+    let mut test = test_drone(1, &[2, 3]);
+    let isolated = Arc::new(Mutex::new(Vec::new()));
+    let hook_isolated = isolated.clone();
+    test.drone.set_isolation_hook(Some(Box::new(move |id| {
+        hook_isolated.lock().unwrap().push(id)
+    })));
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+
+    test.drone.process_command(DroneCommand::RemoveSender(2));
+    test.drone.process_command(DroneCommand::RemoveSender(9));
+    assert!(isolated.lock().unwrap().is_empty());
+    test.drone.process_command(DroneCommand::RemoveSender(3));
+    assert_eq!(*isolated.lock().unwrap(), vec![1]);
+    // Removing an unknown neighbor from an isolated drone does not report it again.
+    test.drone.process_command(DroneCommand::RemoveSender(3));
+    assert_eq!(isolated.lock().unwrap().len(), 1);
+    assert_eq!(
+        telemetry.try_iter().collect::<Vec<_>>(),
+        vec![TelemetryEvent::Isolated]
+    );
+}
+
+#[test]
+fn log_lines_are_tagged_with_the_run_id() {
+    let _ = RUN_ID.set("run-42".to_string());
+    let mut line = Vec::new();
+    format_log_line(
+        &mut line,
+        &mut DeferredNow::new(),
+        &Record::builder()
+            .args(format_args!("Drone 214 started execution."))
+            .level(Level::Info)
+            .target("getdroned")
+            .build(),
+    )
+    .unwrap();
+    let line = String::from_utf8(line).unwrap();
+    assert!(line.contains(" [run-42] ["), "{line}");
+    assert!(line.ends_with("] [getdroned] Drone 214 started execution."));
+}
+
+/// Processes 64 fragments with a drop rate of 0.5 and returns the indexes of those forwarded.
+fn forwarded_indexes(test: &mut TestDrone) -> Vec<u64> {
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.5));
+    for fragment_index in 0..64 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    take(&test.neighbors[&2])
+        .iter()
+        .map(Packet::get_fragment_index)
+        .collect()
+}
+
+#[test]
+fn reseeding_the_rng_makes_the_drops_reproducible() {
+    let mut first = test_drone(1, &[0, 2]);
+    let (telemetry_sender, telemetry) = unbounded();
+    first.drone.set_telemetry_sender(Some(telemetry_sender));
+    first
+        .drone
+        .process_extension_command(GetDronedCommand::ReseedRng(9));
+    assert_eq!(telemetry.try_recv(), Ok(TelemetryEvent::RngReseeded(9)));
+    let expected = forwarded_indexes(&mut first);
+    assert!(!expected.is_empty() && expected.len() < 64);
+
+    let mut second = test_drone(1, &[0, 2]);
+    forwarded_indexes(&mut second);
+    second
+        .drone
+        .process_extension_command(GetDronedCommand::ReseedRng(9));
+    assert_eq!(forwarded_indexes(&mut second), expected);
+}
+
+#[test]
+fn hop_index_is_incremented_once_per_hop() {
+    let mut first = test_drone(1, &[0, 2]);
+    let mut second = test_drone(2, &[1, 3]);
+    first.drone.process_packet(fragment(7, 0, &[0, 1, 2, 3], 1));
+    let mut forwarded = take(&first.neighbors[&2]);
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0].routing_header.hop_index, 2);
+
+    second.drone.process_packet(forwarded.remove(0));
+    let forwarded = take(&second.neighbors[&3]);
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0].routing_header.hop_index, 3);
+    assert!(take(&second.neighbors[&1]).is_empty());
+}
+
+#[test]
+fn crashing_drone_still_forwards_flood_responses() {
+    let mut test = test_drone(1, &[0, 3]);
+    test.drone.set_crashed(true);
+    test.drone.process_packet(flood_response(5, &[3, 1, 0], 1));
+    let forwarded = take(&test.neighbors[&0]);
+    assert_eq!(forwarded.len(), 1);
+    assert!(matches!(
+        forwarded[0].pack_type,
+        PacketType::FloodResponse(_)
+    ));
+    assert_eq!(test.drone.stats().flood_responses_forwarded, 1);
+}
+
+#[test]
+fn flood_responses_queued_at_the_crash_are_forwarded() {
+    let test = test_drone(1, &[0, 3]);
+    test.commands.send(DroneCommand::Crash).unwrap();
+    test.packets.send(flood_response(5, &[3, 1, 0], 1)).unwrap();
+    let running = start(test);
+    let to_initiator = running.neighbors[&0].clone();
+    assert!(running.terminates());
+    assert!(matches!(
+        take(&to_initiator)[..],
+        [Packet {
+            pack_type: PacketType::FloodResponse(_),
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn startup_delay_is_drawn_up_to_the_jitter() {
+    let mut test = test_drone(1, &[]);
+    assert_eq!(test.drone.startup_delay(), Duration::ZERO);
+    let max = Duration::from_millis(10);
+    test.drone.set_startup_jitter(Some(max));
+    test.drone.set_rng_seed(3);
+    let delays: HashSet<Duration> = (0..20).map(|_| test.drone.startup_delay()).collect();
+    assert!(delays.len() > 1);
+    assert!(delays.iter().all(|delay| *delay <= max));
+}
+
+#[test]
+fn recent_outcomes_keep_the_last_decisions() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert!(test.drone.recent_outcomes().is_empty());
+
+    test.drone.set_outcome_history_capacity(2);
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 3], 1));
+    test.drone.process_packet(flood(0, 1, &[0]).0);
+    assert_eq!(
+        test.drone
+            .recent_outcomes()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![
+            ForwardOutcome::Nacked(NackType::ErrorInRouting(3)),
+            ForwardOutcome::FloodForwarded(1),
+        ]
+    );
+}
+
+#[test]
+fn held_drone_applies_its_flood_policy() {
+    let held_outcome = |policy| {
+        let mut test = test_drone(1, &[0, 2]);
+        test.drone.set_held_flood_policy(policy);
+        test.drone.set_held(true);
+        let (packet, request) = flood(0, 1, &[0]);
+        let outcome = test.drone.process_flood_request(packet, request);
+        (
+            outcome,
+            take(&test.neighbors[&0]),
+            take(&test.neighbors[&2]),
+        )
+    };
+
+    let (outcome, to_sender, to_other) = held_outcome(HeldFloodPolicy::Forward);
+    assert_eq!(outcome, ForwardOutcome::FloodForwarded(1));
+    assert!(to_sender.is_empty());
+    assert!(matches!(
+        to_other[..],
+        [Packet {
+            pack_type: PacketType::FloodRequest(_),
+            ..
+        }]
+    ));
+
+    let (outcome, to_sender, to_other) = held_outcome(HeldFloodPolicy::Respond);
+    assert_eq!(outcome, ForwardOutcome::FloodResponded);
+    assert!(matches!(
+        to_sender[..],
+        [Packet {
+            pack_type: PacketType::FloodResponse(_),
+            ..
+        }]
+    ));
+    assert!(to_other.is_empty());
+
+    let (outcome, to_sender, to_other) = held_outcome(HeldFloodPolicy::Ignore);
+    assert_eq!(outcome, ForwardOutcome::Discarded);
+    assert!(to_sender.is_empty() && to_other.is_empty());
+
+    let (outcome, to_sender, to_other) = held_outcome(HeldFloodPolicy::Nack);
+    assert_eq!(outcome, ForwardOutcome::Nacked(NackType::Dropped));
+    assert_eq!(to_sender.len(), 1);
+    assert_eq!(nack_of(&to_sender[0]), Some((NackType::Dropped, 0)));
+    assert_eq!(to_sender[0].routing_header.hops, vec![1, 0]);
+    assert!(to_other.is_empty());
+}
+
+#[test]
+fn flood_policy_only_applies_while_held() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_held_flood_policy(HeldFloodPolicy::Ignore);
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodForwarded(1)
+    );
+}
+
+#[test]
+fn neighbor_status_follows_the_sends() {
+    let mut test = test_drone(1, &[0, 2]);
+    assert_eq!(test.drone.neighbor_status(2), NeighborStatus::Alive);
+    assert_eq!(test.drone.neighbor_status(5), NeighborStatus::Unknown);
+
+    test.neighbors.remove(&2);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert_eq!(test.drone.neighbor_status(2), NeighborStatus::KnownCrashed);
+
+    let (sender, receiver) = unbounded();
+    test.drone
+        .process_command(DroneCommand::AddSender(2, sender));
+    assert_eq!(test.drone.neighbor_status(2), NeighborStatus::Alive);
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(take(&receiver).len(), 1);
+    assert_eq!(test.drone.neighbor_status(2), NeighborStatus::Alive);
+
+    test.drone.process_command(DroneCommand::RemoveSender(2));
+    assert_eq!(test.drone.neighbor_status(2), NeighborStatus::Unknown);
+}
+
+#[test]
+fn traced_drones_append_themselves_to_the_fragment_path() {
+    let traces = PathTraceMap::default();
+    let mut first = test_drone(1, &[0, 2]);
+    let mut second = test_drone(2, &[1, 3]);
+    first.drone.set_path_trace_map(Some(traces.clone()));
+    second.drone.set_path_trace_map(Some(traces.clone()));
+
+    first.drone.process_packet(fragment(7, 0, &[0, 1, 2, 3], 1));
+    second
+        .drone
+        .process_packet(take(&first.neighbors[&2]).remove(0));
+    first.drone.process_packet(fragment(7, 1, &[0, 1, 4], 1));
+    let traces = traces.lock().unwrap();
+    assert_eq!(traces[&(7, 0)], vec![1, 2]);
+    // The path ends at the drone that NACKed the fragment.
+    assert_eq!(traces[&(7, 1)], vec![1]);
+    assert_eq!(traces.len(), 2);
+}
+
+#[test]
+fn reverse_route_goes_back_from_the_current_hop() {
+    let header = |hop_index| SourceRoutingHeader {
+        hop_index,
+        hops: vec![0, 1, 2, 3],
+    };
+    let reversed = GetDroned::reverse_route(&header(2)).unwrap();
+    assert_eq!(reversed.hops, vec![2, 1, 0]);
+    assert_eq!(reversed.hop_index, 0);
+    assert_eq!(GetDroned::reverse_route(&header(0)).unwrap().hops, vec![0]);
+    assert!(GetDroned::reverse_route(&header(4)).is_none());
+}
+
+#[test]
+fn drain_available_takes_the_queued_packets_unprocessed() {
+    let test = test_drone(1, &[0, 2]);
+    assert!(test.drone.drain_available().is_empty());
+    for fragment_index in 0..3 {
+        test.packets
+            .send(fragment(7, fragment_index, &[0, 1, 2], 1))
+            .unwrap();
+    }
+    let drained: Vec<u64> = test
+        .drone
+        .drain_available()
+        .iter()
+        .map(Packet::get_fragment_index)
+        .collect();
+    assert_eq!(drained, vec![0, 1, 2]);
+    assert!(test.drone.drain_available().is_empty());
+    assert!(take(&test.neighbors[&2]).is_empty());
+    assert_eq!(test.drone.stats().packets_received, 0);
+}
+
+#[test]
+fn each_drone_of_the_process_writes_its_own_log_files() {
+    let directory = std::env::temp_dir().join(format!("getdroned_logs_{}", std::process::id()));
+    let writer = Arc::new(DroneLogWriter::new(&directory));
+    let write_line = |writer: &DroneLogWriter, message: &str| {
+        writer
+            .write(
+                &mut DeferredNow::new(),
+                &Record::builder()
+                    .args(format_args!("{message}"))
+                    .level(Level::Info)
+                    .build(),
+            )
+            .unwrap();
+    };
+    let drones: Vec<_> = [3, 4]
+        .into_iter()
+        .map(|id| {
+            let writer = writer.clone();
+            thread::spawn(move || {
+                CURRENT_DRONE.with(|drone| drone.set(Some(id)));
+                write_line(&writer, &format!("Drone {id} started execution."));
+            })
+        })
+        .collect();
+    for drone in drones {
+        drone.join().unwrap();
+    }
+    write_line(&writer, "network initialized");
+    writer.flush().unwrap();
+
+    let mut files: Vec<(String, String)> = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    files.sort();
+    writer.shutdown();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(files.len(), 3);
+    assert!(!files[0].0.starts_with("drone_"));
+    assert!(files[0].1.contains("network initialized"));
+    assert!(files[1].0.starts_with("drone_3"));
+    assert!(files[1].1.contains("Drone 3 started"));
+    assert!(!files[1].1.contains("Drone 4"));
+    assert!(files[2].0.starts_with("drone_4"));
+    assert!(files[2].1.contains("Drone 4 started"));
+}
+
+#[test]
+fn flood_with_a_mismatched_initiator_is_discarded_in_strict_mode() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (packet, mut request) = flood(0, 1, &[0]);
+    request.initiator_id = 5;
+    assert_eq!(
+        test.drone
+            .process_flood_request(packet.clone(), request.clone()),
+        ForwardOutcome::FloodForwarded(1)
+    );
+
+    test.drone.set_strict_flood_validation(true);
+    request.flood_id = 2;
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::Discarded
+    );
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn recording_captures_every_side_effect_in_order() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert!(test.drone.take_timeline().is_empty());
+
+    test.drone.start_recording();
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(1.0));
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 2], 1));
+    assert_timeline(
+        &test.drone.take_timeline(),
+        &[
+            TimelineEntry::Sent {
+                to: 2,
+                session_id: 7,
+            },
+            TimelineEntry::PacketSentEvent(7),
+            TimelineEntry::Outcome(ForwardOutcome::Forwarded(2)),
+            TimelineEntry::Nack {
+                session_id: 8,
+                nack_type: NackType::Dropped,
+            },
+            TimelineEntry::Sent {
+                to: 0,
+                session_id: 8,
+            },
+            TimelineEntry::PacketSentEvent(8),
+            TimelineEntry::PacketDroppedEvent(8),
+            TimelineEntry::Outcome(ForwardOutcome::Dropped),
+        ],
+    );
+    assert!(test.drone.take_timeline().is_empty());
+}
+
+#[test]
+fn routes_longer_than_the_maximum_are_rejected() {
+    let mut test = test_drone(1, &[0, 2]);
+    let mut hops = vec![0, 1, 2];
+    hops.resize(257, 3);
+    test.drone.process_packet(fragment(7, 0, &hops, 1));
+    test.drone.process_packet(flood_response(5, &hops, 1));
+    assert!(take(&test.neighbors[&2]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::Dropped, 0)]);
+    assert_eq!(test.drone.stats().overlong_routes, 2);
+
+    test.drone.set_max_route_hops(None);
+    test.drone.process_packet(fragment(7, 1, &hops, 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+/// Checks of the `tracing` instrumentation, with a subscriber recording every span.
+#[cfg(feature = "tracing")]
+mod spans {
+    use super::*;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Current, Id};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Metadata and recorded fields of a span.
+    type SpanData = (&'static Metadata<'static>, HashMap<&'static str, String>);
+
+    /// Subscriber recording every span with its fields, and tracking the entered spans so that
+    /// `Span::current` works. The ID of a span is its position in `spans`, plus one.
+    #[derive(Clone, Default)]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<SpanData>>>,
+        entered: Arc<Mutex<Vec<u64>>>,
+    }
+
+    /// Visitor storing the debug representation of each field.
+    struct FieldRecorder<'a>(&'a mut HashMap<&'static str, String>);
+
+    impl Visit for FieldRecorder<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = HashMap::new();
+            span.record(&mut FieldRecorder(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((span.metadata(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &tracing::span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let (_, fields) = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut FieldRecorder(fields));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            self.entered.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> Current {
+            match self.entered.lock().unwrap().last() {
+                Some(id) => Current::new(
+                    Id::from_u64(*id),
+                    self.spans.lock().unwrap()[*id as usize - 1].0,
+                ),
+                None => Current::none(),
+            }
+        }
+    }
+
+    /// Name, fragment index, decision and next hop recorded in a span.
+    type SpanSummary = (&'static str, Option<String>, Option<String>, Option<String>);
+
+    /// Runs `scenario` with a `SpanRecorder` as the default subscriber.
+    ///
+    /// # Returns
+    /// The summary of each span, in opening order.
+    fn recorded_spans(scenario: impl FnOnce()) -> Vec<SpanSummary> {
+        let recorder = SpanRecorder::default();
+        let spans = recorder.spans.clone();
+        tracing::subscriber::with_default(recorder, scenario);
+        let spans = spans.lock().unwrap();
+        spans
+            .iter()
+            .map(|(metadata, fields)| {
+                (
+                    metadata.name(),
+                    fields.get("fragment_index").cloned(),
+                    fields.get("decision").cloned(),
+                    fields.get("next_hop").cloned(),
+                )
+            })
+            .collect()
+    }
+
+    /// Expected span of fragment 0 of a session.
+    fn span(decision: &str, next_hop: Option<&str>) -> SpanSummary {
+        (
+            "process_packet",
+            Some("0".to_string()),
+            Some(decision.to_string()),
+            next_hop.map(str::to_string),
+        )
+    }
+
+    #[test]
+    fn processed_fragment_gets_a_span() {
+        let spans = recorded_spans(|| {
+            let mut test = test_drone(1, &[0, 2]);
+            test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+        });
+        assert_eq!(spans, vec![span("Forwarded(2)", Some("2"))]);
+    }
+
+    #[test]
+    fn released_fragment_gets_its_own_span() {
+        let spans = recorded_spans(|| {
+            let mut test = test_drone(1, &[0, 2]);
+            test.drone.set_held(true);
+            test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+            test.drone.set_held(false);
+        });
+        assert_eq!(
+            spans,
+            vec![span("Held", None), span("Forwarded(2)", Some("2"))]
+        );
+    }
+
+    #[test]
+    fn retried_fragment_gets_its_own_span() {
+        let spans = recorded_spans(|| {
+            let mut test = test_drone(1, &[0, 2]);
+            test.drone
+                .set_drop_model(DropModel::BurstCycle { drop: 1, pass: 1 });
+            test.drone
+                .set_drop_retry_delay(Some(Duration::from_secs(1)));
+            test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+            test.drone.flush_pending();
+        });
+        assert_eq!(
+            spans,
+            vec![span("RetryDeferred", None), span("Forwarded(2)", Some("2"))]
+        );
+    }
+}
+
+/// Returns the packet drop rates set by the commands of the history, in the order applied.
+fn applied_drop_rates(drone: &GetDroned) -> Vec<f32> {
+    drone
+        .command_history()
+        .iter()
+        .filter_map(|(_, command)| match command {
+            CommandRecord::SetPacketDropRate(pdr) => Some(*pdr),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn queued_commands_are_applied_in_a_shuffled_batch() {
+    let mut test = test_drone(1, &[]);
+    test.drone.set_rng_seed(5);
+    test.drone.set_command_history_capacity(16);
+    test.drone.set_command_reorder_batch(Some(8));
+    for pdr in [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9] {
+        test.commands
+            .send(DroneCommand::SetPacketDropRate(pdr))
+            .unwrap();
+    }
+    test.drone
+        .process_reordered_commands(DroneCommand::SetPacketDropRate(0.0));
+
+    let mut applied = applied_drop_rates(&test.drone);
+    assert_ne!(applied, [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7]);
+    applied.sort_by(f32::total_cmp);
+    assert_eq!(applied, [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7]);
+    // The commands beyond the batch are left on the channel.
+    assert_eq!(test.commands.len(), 2);
+}
+
+#[test]
+fn commands_after_a_crash_of_the_batch_are_ignored() {
+    let mut test = test_drone(1, &[]);
+    test.drone.set_rng_seed(5);
+    test.drone.set_command_history_capacity(16);
+    test.drone.set_command_reorder_batch(Some(8));
+    for pdr in [0.1, 0.2, 0.3, 0.4, 0.5, 0.6] {
+        test.commands
+            .send(DroneCommand::SetPacketDropRate(pdr))
+            .unwrap();
+    }
+    test.commands.send(DroneCommand::Crash).unwrap();
+    test.drone
+        .process_reordered_commands(DroneCommand::SetPacketDropRate(0.0));
+
+    assert!(test.drone.is_crashed());
+    let history = test.drone.command_history();
+    assert!(matches!(history.back(), Some((_, CommandRecord::Crash))));
+    assert_eq!(
+        test.drone.config().packet_drop_rate,
+        applied_drop_rates(&test.drone)
+            .last()
+            .copied()
+            .unwrap_or(0.0)
+    );
+}
+
+#[test]
+fn crash_state_is_exposed() {
+    let mut test = test_drone(1, &[0]);
+    assert!(!test.drone.is_crashed());
+    test.drone.process_command(DroneCommand::Crash);
+    assert!(test.drone.is_crashed());
+}
+
+#[test]
+fn highest_flood_id_is_tracked_per_initiator() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    for (initiator, flood_id) in [(0, 4), (0, 9), (0, 2), (3, 1)] {
+        let (packet, request) = flood(initiator, flood_id, &[initiator]);
+        test.drone.process_flood_request(packet, request);
+    }
+    assert_eq!(test.drone.max_flood_id(0), Some(9));
+    assert_eq!(test.drone.max_flood_id(3), Some(1));
+    assert_eq!(test.drone.max_flood_id(2), None);
+}
+
+#[test]
+fn events_are_sent_in_batches() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (batch_sender, batches) = unbounded();
+    test.drone.set_event_batching(Some(batch_sender), 2);
+    for fragment_index in 0..3 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    assert!(test.events.try_recv().is_err());
+    assert_eq!(
+        batches
+            .try_iter()
+            .map(|batch| batch.len())
+            .collect::<Vec<_>>(),
+        vec![2]
+    );
+
+    // The pending event is sent on the next tick, or before the drone terminates.
+    test.drone.flush_events();
+    assert_eq!(
+        batches
+            .try_iter()
+            .map(|batch| batch.len())
+            .collect::<Vec<_>>(),
+        vec![1]
+    );
+    test.drone.flush_events();
+    assert!(batches.try_recv().is_err());
+
+    test.drone.process_packet(fragment(7, 3, &[0, 1, 2], 1));
+    test.drone.set_event_batching(None, 1);
+    assert!(matches!(
+        test.events.try_recv(),
+        Ok(DroneEvent::PacketSent(_))
+    ));
+}
+
+#[test]
+fn fragments_forwarded_to_endpoints_are_reported() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    test.drone
+        .set_neighbor_types(HashMap::from([(2, NodeType::Server)]));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 3], 1));
+    // The type of neighbor 0 is learned from its flood request.
+    test.drone.process_packet(flood(0, 1, &[0]).0);
+    test.drone.process_packet(fragment(8, 0, &[3, 1, 0], 1));
+    assert_eq!(
+        telemetry.try_iter().collect::<Vec<_>>(),
+        vec![
+            TelemetryEvent::DeliveredToEndpoint {
+                session_id: 7,
+                fragment_index: 0,
+                endpoint: 2,
+            },
+            TelemetryEvent::DeliveredToEndpoint {
+                session_id: 8,
+                fragment_index: 0,
+                endpoint: 0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn nacks_beyond_the_rate_limit_are_suppressed() {
+    let mut test = test_drone(1, &[0]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone.set_nack_rate_limit(Some(2));
+    for fragment_index in 0..5 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 9], 1));
+    }
+    assert_eq!(take(&test.neighbors[&0]).len(), 2);
+    assert_eq!(test.drone.stats.nacks_sent, 2);
+    assert_eq!(test.drone.stats.nacks_suppressed, 3);
+
+    // Half a second refills one token.
+    clock.advance(Duration::from_millis(500));
+    for fragment_index in 5..7 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 9], 1));
+    }
+    assert_eq!(take(&test.neighbors[&0]).len(), 1);
+    assert_eq!(test.drone.stats.nacks_suppressed, 4);
+}
+
+#[test]
+fn removed_neighbors_are_listed_until_they_expire() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone
+        .set_removed_neighbor_retention(Duration::from_secs(10));
+    test.drone.process_command(DroneCommand::RemoveSender(3));
+    clock.advance(Duration::from_secs(6));
+    test.drone.process_command(DroneCommand::RemoveSender(2));
+    test.drone.process_command(DroneCommand::RemoveSender(9));
+    assert_eq!(test.drone.recently_removed_neighbors(), vec![2, 3]);
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(test.drone.recently_removed_neighbors(), vec![2]);
+
+    // A neighbor added back is no longer listed.
+    let (sender, _receiver) = unbounded();
+    test.drone
+        .process_command(DroneCommand::AddSender(2, sender));
+    assert!(test.drone.recently_removed_neighbors().is_empty());
+
+    test.drone.process_command(DroneCommand::RemoveSender(0));
+    test.drone.clear_recently_removed_neighbors();
+    assert!(test.drone.recently_removed_neighbors().is_empty());
+}
+
+#[test]
+fn tap_receives_a_copy_of_each_forwarded_fragment() {
+    let mut test = test_drone(1, &[0, 2, 4]);
+    test.drone.set_tap(Some(4));
+    for fragment_index in 0..2 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    // Fragments forwarded to the tap itself are not copied.
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 4], 1));
+
+    let forwarded = take(&test.neighbors[&2]);
+    assert_eq!(forwarded.len(), 2);
+    assert!(forwarded
+        .iter()
+        .all(|packet| packet.routing_header.hop_index == 2));
+    let copies = take(&test.neighbors[&4]);
+    assert_eq!(copies.len(), 3);
+    for (copy, fragment_index) in copies[..2].iter().zip(0..) {
+        assert_eq!(copy.session_id, 7);
+        assert_eq!(copy.get_fragment_index(), fragment_index);
+        assert_eq!(copy.routing_header.hops, vec![1, 4]);
+        assert_eq!(copy.routing_header.hop_index, 1);
+    }
+    assert_eq!(copies[2].session_id, 8);
+    assert_eq!(copies[2].routing_header.hops, vec![0, 1, 4]);
+    assert_eq!(test.drone.stats.fragments_forwarded, 3);
+
+    test.drone.set_tap(None);
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 2], 1));
+    assert!(take(&test.neighbors[&4]).is_empty());
+}
+
+#[test]
+fn replayed_commands_configure_the_drone() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (sender, receiver) = unbounded();
+    test.drone.replay_commands(
+        &[
+            CommandRecord::SetPacketDropRate(0.25),
+            CommandRecord::RemoveSender(2),
+            CommandRecord::AddSender(3),
+            // Skipped: no channel is supplied for neighbor 4.
+            CommandRecord::AddSender(4),
+        ],
+        &HashMap::from([(3, sender)]),
+    );
+    assert_eq!(test.drone.packet_drop_rate, 0.25);
+    let mut neighbors: Vec<NodeId> = test.drone.packet_senders.keys().copied().collect();
+    neighbors.sort_unstable();
+    assert_eq!(neighbors, vec![0, 3]);
+    // The added neighbor uses the supplied channel.
+    test.drone.packet_senders[&3]
+        .send(fragment(7, 0, &[1, 3], 1))
+        .unwrap();
+    assert_eq!(take(&receiver).len(), 1);
+}
+
+#[test]
+fn command_file_is_loaded_and_replayed() {
+    let path = std::env::temp_dir().join(format!("getdroned_commands_{}.toml", std::process::id()));
+    fs::write(
+        &path,
+        "commands = [{ SetPacketDropRate = 0.5 }, { RemoveSender = 2 }]\n",
+    )
+    .unwrap();
+    let commands = crate::command::load_command_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        commands,
+        vec![
+            CommandRecord::SetPacketDropRate(0.5),
+            CommandRecord::RemoveSender(2)
+        ]
+    );
+
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.replay_commands(&commands, &HashMap::new());
+    assert_eq!(test.drone.packet_drop_rate, 0.5);
+    assert!(!test.drone.packet_senders.contains_key(&2));
+}
+
+#[test]
+fn high_drop_ratio_makes_the_drone_unhealthy() {
+    let mut test = test_drone(1, &[0, 2]);
+    assert!(test.drone.health().healthy);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(1.0));
+    for fragment_index in 1..4 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    let health = test.drone.health();
+    assert!(!health.healthy);
+    assert_eq!(health.level, DroneHealth::Degraded);
+    assert_eq!(
+        health.reasons,
+        vec!["drop ratio 0.75 over 0.50".to_string()]
+    );
+
+    test.drone.process_command(DroneCommand::Crash);
+    let health = test.drone.health();
+    assert_eq!(health.level, DroneHealth::Dead);
+    assert_eq!(health.reasons[0], "crashed");
+}
+
+#[test]
+fn idle_time_is_measured_from_the_last_packet() {
+    let mut test = test_drone(1, &[0, 2]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone.set_health_thresholds(HealthThresholds {
+        max_drop_ratio: 1.0,
+        max_idle: Some(Duration::from_secs(5)),
+    });
+    clock.advance(Duration::from_secs(4));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    clock.advance(Duration::from_secs(5));
+    assert!(test.drone.health().healthy);
+
+    clock.advance(Duration::from_secs(1));
+    let health = test.drone.health();
+    assert!(!health.healthy);
+    assert_eq!(health.reasons, vec!["idle for 6s over 5s".to_string()]);
+}
+
+#[test]
+fn forwarded_headers_point_at_the_receiving_neighbor() {
+    // The headers are checked by the debug assertions of `send_packet` as well.
+    let mut test = test_drone(1, &[0, 2, 4, 5]);
+    test.drone.set_default_route(Some(4));
+    test.drone
+        .set_next_hop_resolver(Some(Box::new(|destination| {
+            (destination == 8).then_some(5)
+        })));
+    let mut ack = fragment(7, 0, &[0, 1, 2], 1);
+    ack.pack_type = PacketType::Ack(Ack { fragment_index: 0 });
+    let packets = [
+        fragment(7, 0, &[0, 1, 2], 1),
+        fragment(7, 1, &[3, 0, 1, 2, 6], 2),
+        ack,
+        flood_response(3, &[2, 1, 0], 1),
+        fragment(7, 2, &[0, 1, 9], 1),
+        fragment(7, 3, &[0, 1, 8], 1),
+    ];
+    for packet in packets {
+        test.drone.process_packet(packet);
+    }
+    for (neighbor, expected) in [(2, 3), (0, 1), (4, 1), (5, 1)] {
+        let received = take(&test.neighbors[&neighbor]);
+        assert_eq!(received.len(), expected, "packets sent to {neighbor}");
+        for packet in received {
+            let header = &packet.routing_header;
+            assert_eq!(header.hops[header.hop_index], neighbor);
+            assert_eq!(header.hops[header.hop_index - 1], 1);
+        }
+    }
+}
+
+#[test]
+fn nacks_referring_to_the_drone_are_counted_and_forwarded() {
+    let mut test = test_drone(1, &[0, 2]);
+    let nack = |hops: &[NodeId], hop_index, nack_type| {
+        Packet::new_nack(
+            SourceRoutingHeader {
+                hop_index,
+                hops: hops.to_vec(),
+            },
+            7,
+            Nack {
+                fragment_index: 0,
+                nack_type,
+            },
+        )
+    };
+    test.drone
+        .process_packet(nack(&[3, 2, 1, 0], 2, NackType::Dropped));
+    assert_eq!(test.drone.stats.self_referential_nacks, 0);
+    // Originated by the drone, coming back through a loop.
+    test.drone
+        .process_packet(nack(&[1, 2, 1, 0], 2, NackType::Dropped));
+    test.drone
+        .process_packet(nack(&[3, 2, 1, 0], 2, NackType::ErrorInRouting(1)));
+    assert_eq!(test.drone.stats.self_referential_nacks, 2);
+
+    let forwarded: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(
+        forwarded,
+        vec![
+            (NackType::Dropped, 0),
+            (NackType::Dropped, 0),
+            (NackType::ErrorInRouting(1), 0)
+        ]
+    );
+}
+
+#[test]
+fn reset_stats_clears_every_measurement() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    test.drone.set_event_log_capacity(8);
+    test.drone.set_send_block_tracking(true);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(flood(0, 1, &[0]).0);
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(1.0));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(test.drone.session_loss(7), Some(1));
+    assert_eq!(test.drone.flood_fanout(0, 1), Some(2));
+    assert!(!test.drone.recent_events().is_empty());
+    let before = test.drone.stats().clone();
+    assert_ne!(before, DroneStats::default());
+
+    test.drone
+        .process_extension_command(GetDronedCommand::ResetStats);
+    assert!(telemetry
+        .try_iter()
+        .any(|event| event == TelemetryEvent::StatsReset(before.clone())));
+    assert_eq!(test.drone.stats(), &DroneStats::default());
+    assert_eq!(test.drone.stats().average_path_length(), 0.0);
+    assert_eq!(
+        test.drone.route_length_histogram(),
+        &RouteLengthHistogram::default()
+    );
+    assert_eq!(test.drone.session_loss(7), None);
+    assert_eq!(test.drone.flood_fanout(0, 1), None);
+    assert_eq!(test.drone.role_summary(), RoleSummary::default());
+    assert_eq!(test.drone.total_send_block_time(), Duration::ZERO);
+    assert!(test.drone.drop_rate_window.is_none());
+    assert!(test.drone.recent_events().is_empty());
+    // Settings and neighbors are kept.
+    assert_eq!(test.drone.packet_drop_rate, 1.0);
+    assert_eq!(test.drone.flood_targets(0), vec![2, 3]);
+}
+
+/// Creates a held drone with two held fragments and a deferred flood request, all bound to
+/// neighbor 2.
+fn drone_with_pending_packets() -> TestDrone {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_clock(Arc::new(ManualClock::new()));
+    test.drone.set_flood_jitter(Some(Duration::from_secs(1)));
+    test.drone.set_held(true);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodDeferred
+    );
+    assert!(take(&test.neighbors[&2]).is_empty());
+    test
+}
+
+#[test]
+fn flush_pending_sends_the_pending_packets() {
+    let mut test = drone_with_pending_packets();
+    test.drone.flush_pending();
+    let sent = take(&test.neighbors[&2]);
+    assert_eq!(sent.len(), 3);
+    assert_eq!(
+        sent.iter()
+            .filter(|packet| matches!(packet.pack_type, PacketType::MsgFragment(_)))
+            .map(Packet::get_fragment_index)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    assert!(sent
+        .iter()
+        .any(|packet| matches!(packet.pack_type, PacketType::FloodRequest(_))));
+    assert!(take(&test.neighbors[&0]).is_empty());
+
+    // The drone is released: new fragments are forwarded right away.
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn flush_pending_dead_letters_the_pending_packets() {
+    let mut test = drone_with_pending_packets();
+    test.drone
+        .set_pending_flush_policy(PendingFlushPolicy::DeadLetter);
+    test.drone.flush_pending();
+    assert!(take(&test.neighbors[&2]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::Dropped, 0), (NackType::Dropped, 1)]);
+    let dropped: Vec<_> = test
+        .events
+        .try_iter()
+        .filter_map(|event| match event {
+            DroneEvent::PacketDropped(packet) => Some(packet),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(dropped.len(), 3);
+    assert!(matches!(dropped[2].pack_type, PacketType::FloodRequest(_)));
+    assert_eq!(test.drone.stats().fragments_dropped, 2);
+}
+
+#[test]
+fn flood_jitter_spreads_the_forwards_in_time() {
+    let mut test = test_drone(1, &[0, 2]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone.set_rng_seed(7);
+    test.drone.set_flood_jitter(Some(Duration::from_secs(1)));
+    for flood_id in 0..10 {
+        let (packet, request) = flood(0, flood_id, &[0]);
+        assert_eq!(
+            test.drone.process_flood_request(packet, request),
+            ForwardOutcome::FloodDeferred
+        );
+    }
+    assert!(take(&test.neighbors[&2]).is_empty());
+
+    let mut forwards_per_tick = Vec::new();
+    for _ in 0..10 {
+        clock.advance(Duration::from_millis(100));
+        test.drone.send_due_floods();
+        forwards_per_tick.push(take(&test.neighbors[&2]).len());
+    }
+    assert_eq!(forwards_per_tick.iter().sum::<usize>(), 10);
+    assert!(forwards_per_tick.iter().filter(|count| **count > 0).count() > 1);
+    assert!(test.drone.pending_floods.is_empty());
+}
+
+#[test]
+fn mis_addressed_first_hop_is_redirected_under_the_option() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    test.drone.process_packet(fragment(7, 0, &[3, 1, 2], 0));
+    let nacks: Vec<_> = take(&test.neighbors[&3]).iter().map(nack_of).collect();
+    assert_eq!(nacks, vec![Some((NackType::UnexpectedRecipient(1), 0))]);
+    assert!(take(&test.neighbors[&2]).is_empty());
+
+    test.drone.set_first_hop_correction(true);
+    test.drone.process_packet(fragment(7, 1, &[3, 1, 2], 0));
+    let redirected = take(&test.neighbors[&3]);
+    assert_eq!(redirected.len(), 1);
+    assert_eq!(redirected[0].routing_header.hops, vec![3, 1, 2]);
+    assert_eq!(redirected[0].routing_header.hop_index, 0);
+    assert_eq!(redirected[0].get_fragment_index(), 1);
+    assert!(take(&test.neighbors[&2]).is_empty());
+
+    // The intended first hop must be a neighbor.
+    test.drone.process_packet(fragment(7, 2, &[9, 1, 2], 0));
+    assert!(take(&test.neighbors[&3]).is_empty());
+    assert!(take(&test.neighbors[&2]).is_empty());
+    assert_eq!(test.drone.stats.fragments_forwarded, 0);
+}
+
+#[test]
+fn route_lengths_of_processed_packets_are_tracked() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone
+        .process_packet(fragment(7, 1, &[5, 4, 3, 0, 1, 2], 4));
+    test.drone.process_packet(fragment(
+        7,
+        2,
+        &[13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 0, 1, 2],
+        12,
+    ));
+    test.drone.process_packet(fragment(7, 3, &[0, 1], 1));
+    // Flood requests have no route.
+    test.drone.process_packet(flood(0, 1, &[0]).0);
+    assert_eq!(test.drone.route_length_histogram().counts, [1, 1, 1, 1, 0]);
+}
+
+#[test]
+fn flood_targets_exclude_the_sender() {
+    let mut test = test_drone(1, &[4, 0, 3, 2]);
+    assert_eq!(test.drone.flood_targets(0), vec![2, 3, 4]);
+    assert_eq!(test.drone.flood_targets(9), vec![0, 2, 3, 4]);
+    test.drone.process_command(DroneCommand::RemoveSender(3));
+    assert_eq!(test.drone.flood_targets(0), vec![2, 4]);
+
+    // The flood request is forwarded to the predicted neighbors only.
+    test.drone.process_packet(flood(0, 1, &[0]).0);
+    for (neighbor, expected) in [(0, 0), (2, 1), (4, 1)] {
+        assert_eq!(take(&test.neighbors[&neighbor]).len(), expected);
+    }
+}
+
+#[test]
+fn forward_workers_keep_the_order_of_each_session() {
+    let mut test = test_drone(1, &[0]);
+    // A full bounded channel would block the drone loop without the workers.
+    let (slow_sender, slow_receiver) = bounded(1);
+    test.drone.packet_senders.insert(2, slow_sender);
+    test.drone.set_forward_workers(2);
+    test.drone.spawn_forward_workers();
+    for fragment_index in 0..5 {
+        for session in [7, 8, 9] {
+            assert_eq!(
+                test.drone
+                    .process_fragment(fragment(session, fragment_index, &[0, 1, 2], 1)),
+                ForwardOutcome::Forwarded(2)
+            );
+        }
+    }
+
+    let mut received: HashMap<u64, Vec<u64>> = HashMap::new();
+    for _ in 0..15 {
+        let packet = slow_receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        received
+            .entry(packet.session_id)
+            .or_default()
+            .push(packet.get_fragment_index());
+    }
+    for session in [7, 8, 9] {
+        assert_eq!(received[&session], vec![0, 1, 2, 3, 4]);
+    }
+
+    // Every send is reported back to the drone loop, a failed one with the fragment as received.
+    drop(slow_receiver);
+    test.drone.process_packet(fragment(7, 5, &[0, 1, 2], 1));
+    let results: Vec<ForwardResult> = (0..16)
+        .map(|_| {
+            test.drone
+                .forward_results
+                .recv_timeout(Duration::from_secs(1))
+                .unwrap()
+        })
+        .collect();
+    assert!(results.iter().all(|(target, _)| *target == 2));
+    let failed: Vec<_> = results
+        .iter()
+        .filter_map(|(_, result)| result.as_ref().err())
+        .collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].routing_header.hop_index, 1);
+}
+
+#[test]
+fn forward_workers_are_settled_when_the_drone_stops() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    test.drone.set_forward_workers(2);
+    test.drone.spawn_forward_workers();
+    // The neighbor 2 crashed: its channel is disconnected.
+    test.neighbors.remove(&2);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 3], 1));
+    test.drone.finish_execution();
+
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::ErrorInRouting(2), 0)]);
+    assert_eq!(take(&test.neighbors[&3]).len(), 1);
+    // Only the fragment actually sent by a worker is reported as sent, besides the NACK.
+    let sent: Vec<_> = test
+        .events
+        .try_iter()
+        .filter_map(|event| match event {
+            DroneEvent::PacketSent(packet) => Some((packet.session_id, nack_of(&packet))),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sent.len(), 2);
+    assert!(sent.contains(&(7, Some((NackType::ErrorInRouting(2), 0)))));
+    assert!(sent.contains(&(8, None)));
+}
+
+#[test]
+fn pdr_change_hook_sees_the_old_and_new_rates() {
+    let mut test = test_drone(1, &[0]);
+    let (change_sender, changes) = unbounded();
+    test.drone
+        .set_pdr_change_hook(Some(Box::new(move |old, new| {
+            change_sender.send((old, new)).unwrap();
+        })));
+    for pdr in [0.25, 0.25, 0.5, 0.0] {
+        test.drone
+            .process_command(DroneCommand::SetPacketDropRate(pdr));
+    }
+    assert_eq!(
+        changes.try_iter().collect::<Vec<_>>(),
+        vec![(0.0, 0.25), (0.25, 0.5), (0.5, 0.0)]
+    );
+
+    test.drone.set_pdr_change_hook(None);
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.75));
+    assert!(changes.try_recv().is_err());
+}
+
+#[test]
+fn config_dump_is_logged_in_a_single_entry() {
+    capture_logs();
+    let mut test = test_drone(232, &[3, 2]);
+    test.drone
+        .set_neighbor_types(HashMap::from([(2, NodeType::Server)]));
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.25));
+    test.drone
+        .process_extension_command(GetDronedCommand::DumpConfig);
+
+    let dumps: Vec<_> = logged(232)
+        .into_iter()
+        .filter(|(_, _, message)| message.contains("configuration dump"))
+        .collect();
+    assert_eq!(dumps.len(), 1);
+    let (_, level, dump) = &dumps[0];
+    assert_eq!(*level, Level::Info);
+    for field in [
+        "crashed: false",
+        "neighbor types: [(2, Server)]",
+        "packet_drop_rate: 0.25",
+        "leaf_flood_policy:",
+        "neighbors: [\n        2,\n        3,\n    ]",
+    ] {
+        assert!(dump.contains(field), "{field:?} missing from {dump}");
+    }
+}
+
+#[test]
+fn full_bounded_channel_is_nacked_without_blocking() {
+    let mut test = test_drone(1, &[0]);
+    let (full_sender, full_receiver) = bounded(1);
+    test.drone.packet_senders.insert(2, full_sender);
+    test.drone.set_bounded_channels(true);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(full_receiver.len(), 1);
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::ErrorInRouting(2), 1)]);
+    // A full channel does not mean that the neighbor crashed.
+    assert_eq!(test.drone.neighbor_status(2), NeighborStatus::Alive);
+
+    // Once the neighbor catches up, the sends succeed again.
+    full_receiver.recv().unwrap();
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 2], 1));
+    assert_eq!(full_receiver.len(), 1);
+}
+
+#[test]
+fn unbounded_mode_waits_for_a_full_channel() {
+    let mut test = test_drone(1, &[0]);
+    let (full_sender, full_receiver) = bounded(1);
+    test.drone.packet_senders.insert(2, full_sender);
+    let consumer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        (0..2)
+            .map(|_| full_receiver.recv().unwrap().get_fragment_index())
+            .collect::<Vec<_>>()
+    });
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert_eq!(consumer.join().unwrap(), vec![0, 1]);
+    assert!(take(&test.neighbors[&0]).is_empty());
+}
+
+#[test]
+fn session_loss_counts_the_fragments_not_forwarded() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 9], 1));
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(1.0));
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(7, 3, &[0, 1, 2], 1));
+    assert_eq!(test.drone.session_loss(7), Some(3));
+    assert_eq!(test.drone.session_loss(8), None);
+
+    // Only the last sessions are tracked.
+    for session_id in 100..100 + MAX_TRACKED_LOSS_SESSIONS as u64 {
+        test.drone
+            .process_packet(fragment(session_id, 0, &[0, 1, 2], 1));
+    }
+    assert_eq!(test.drone.session_loss(7), None);
+    assert_eq!(test.drone.session_loss(100), Some(1));
+    assert_eq!(test.drone.session_losses.len(), MAX_TRACKED_LOSS_SESSIONS);
+}
+
+#[test]
+fn duplicate_flood_responses_are_suppressed_under_the_option() {
+    let mut test = test_drone(1, &[0, 2]);
+    for _ in 0..2 {
+        test.drone.process_packet(flood_response(3, &[2, 1, 0], 1));
+    }
+    assert_eq!(take(&test.neighbors[&0]).len(), 2);
+
+    test.drone.set_flood_response_dedup(true);
+    for flood_id in [3, 3, 4] {
+        test.drone
+            .process_packet(flood_response(flood_id, &[2, 1, 0], 1));
+    }
+    assert_eq!(take(&test.neighbors[&0]).len(), 2);
+
+    // Only the last responses are remembered.
+    for flood_id in 100..100 + MAX_TRACKED_FLOOD_RESPONSES as u64 {
+        test.drone
+            .process_packet(flood_response(flood_id, &[2, 1, 0], 1));
+    }
+    assert_eq!(
+        test.drone.forwarded_flood_responses.len(),
+        MAX_TRACKED_FLOOD_RESPONSES
+    );
+    take(&test.neighbors[&0]);
+    test.drone.process_packet(flood_response(3, &[2, 1, 0], 1));
+    assert_eq!(take(&test.neighbors[&0]).len(), 1);
+}
+
+#[test]
+fn headroom_is_the_smallest_remaining_capacity() {
+    let mut test = test_drone(1, &[0]);
+    assert_eq!(test.drone.min_neighbor_headroom(), None);
+    let (sender_2, _receiver_2) = bounded(4);
+    let (sender_3, _receiver_3) = bounded(3);
+    test.drone.packet_senders.insert(2, sender_2);
+    test.drone.packet_senders.insert(3, sender_3);
+    assert_eq!(test.drone.min_neighbor_headroom(), Some(3));
+    for fragment_index in 0..3 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    assert_eq!(test.drone.min_neighbor_headroom(), Some(1));
+    test.drone.process_packet(fragment(7, 3, &[0, 1, 3], 1));
+    assert_eq!(test.drone.min_neighbor_headroom(), Some(1));
+    test.drone.process_packet(fragment(7, 4, &[0, 1, 3], 1));
+    test.drone.process_packet(fragment(7, 5, &[0, 1, 3], 1));
+    assert_eq!(test.drone.min_neighbor_headroom(), Some(0));
+}
+
+#[test]
+fn truncated_flood_replay_is_discarded_with_the_trace_check() {
+    capture_logs();
+    let mut test = test_drone(234, &[0, 2, 3]);
+    test.drone.set_flood_trace_check(true);
+    test.drone.set_strict_flood_validation(true);
+    let (packet, request) = flood(5, 1, &[5, 6, 7, 2]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodForwarded(2)
+    );
+    // A copy from another neighbor is not compared.
+    let (packet, request) = flood(5, 1, &[5, 3]);
+    assert_ne!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::Discarded
+    );
+    let (packet, request) = flood(5, 1, &[5, 7, 2]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::Discarded
+    );
+    assert!(
+        logged(234)
+            .iter()
+            .any(|(_, level, message)| *level == Level::Warn
+                && message.contains("shorter path trace"))
+    );
+
+    // Only the last traces are remembered.
+    for flood_id in 100..100 + MAX_TRACKED_FLOOD_TRACES as u64 {
+        let (packet, request) = flood(5, flood_id, &[5, 2]);
+        test.drone.process_flood_request(packet, request);
+    }
+    assert_eq!(
+        test.drone.flood_trace_lengths.len(),
+        MAX_TRACKED_FLOOD_TRACES
+    );
+    let (packet, request) = flood(5, 1, &[5, 7, 2]);
+    assert_ne!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::Discarded
+    );
+}
+
+#[test]
+fn event_log_keeps_the_last_events() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert!(test.drone.recent_events().is_empty());
+
+    test.drone.set_event_log_capacity(3);
+    for fragment_index in 1..4 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(1.0));
+    test.drone.process_packet(fragment(7, 4, &[0, 1, 2], 1));
+    let events = test.drone.recent_events();
+    assert_eq!(events.len(), 3);
+    assert!(matches!(
+        &events[..],
+        [
+            DroneEvent::PacketSent(forwarded),
+            DroneEvent::PacketSent(nack),
+            DroneEvent::PacketDropped(dropped),
+        ] if forwarded.get_fragment_index() == 3
+            && nack_of(nack) == Some((NackType::Dropped, 4))
+            && dropped.get_fragment_index() == 4
+    ));
+    // The controller received the events as well.
+    assert_eq!(test.events.try_iter().count(), 6);
+
+    test.drone.set_event_log_capacity(1);
+    assert_eq!(test.drone.recent_events().len(), 1);
+}
+
+#[test]
+fn shortcut_reasons_are_told_apart() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    let ack = |hops: &[NodeId], hop_index| {
+        Packet::new_ack(
+            SourceRoutingHeader {
+                hop_index,
+                hops: hops.to_vec(),
+            },
+            7,
+            0,
+        )
+    };
+    test.drone.send_packet(ack(&[1], 0));
+    test.drone.process_packet(ack(&[0, 1], 1));
+    test.drone.process_packet(ack(&[0, 3, 2], 1));
+    drop(test.neighbors.remove(&2));
+    test.drone.process_packet(ack(&[0, 1, 2], 1));
+
+    let reasons: Vec<_> = telemetry
+        .try_iter()
+        .filter_map(|event| match event {
+            TelemetryEvent::ControllerShortcut { reason, .. } => Some(reason),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![
+            ShortcutReason::NoNextHop,
+            ShortcutReason::InvalidRoute(NackType::DestinationIsDrone),
+            ShortcutReason::InvalidRoute(NackType::UnexpectedRecipient(1)),
+            ShortcutReason::SendFailed(2),
+        ]
+    );
+    assert_eq!(
+        test.events
+            .try_iter()
+            .filter(|event| matches!(event, DroneEvent::ControllerShortcut(_)))
+            .count(),
+        4
+    );
+}
+
+#[test]
+fn config_snapshot_round_trips_through_serde() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.25));
+    test.drone.set_default_route(Some(2));
+    test.drone
+        .set_nack_aggregation_window(Some(Duration::from_millis(150)));
+    test.drone.set_tap(Some(0));
+    let snapshot = test.drone.config();
+    let serialized = toml::to_string(&snapshot).unwrap();
+    assert_eq!(
+        toml::from_str::<DroneConfigSnapshot>(&serialized).unwrap(),
+        snapshot
+    );
+}
+
+#[test]
+fn config_snapshot_rejects_malformed_input() {
+    let valid = toml::to_string(&test_drone(1, &[0]).drone.config()).unwrap();
+    let inputs = [
+        String::new(),
+        "\u{0}\u{ff}garbage".to_string(),
+        "id = 300".to_string(),
+        valid[..valid.len() / 2].to_string(),
+        valid.replace("packet_drop_rate", "packet_drop_rat"),
+    ];
+    for input in inputs {
+        assert!(toml::from_str::<DroneConfigSnapshot>(&input).is_err());
+    }
+}
+
+#[test]
+fn config_snapshot_round_trips_for_random_settings() {
+    let mut rng = StdRng::seed_from_u64(236);
+    for _ in 0..256 {
+        let mut test = test_drone(rng.gen_range(3..=NodeId::MAX), &[0, 2]);
+        test.drone
+            .process_command(DroneCommand::SetPacketDropRate(rng.gen()));
+        test.drone.set_header_corruption_rate(rng.gen());
+        test.drone
+            .set_default_route(rng.gen::<bool>().then(|| rng.gen()));
+        test.drone.set_nack_aggregation_window(
+            rng.gen::<bool>().then(|| Duration::from_nanos(rng.gen())),
+        );
+        test.drone
+            .set_max_active_sessions(rng.gen::<bool>().then(|| rng.gen_range(0..1024)));
+        test.drone
+            .set_session_idle_timeout(Duration::from_millis(rng.gen()));
+        test.drone
+            .set_command_history_capacity(rng.gen_range(0..1024));
+        test.drone
+            .set_nack_rate_limit(rng.gen::<bool>().then(|| rng.gen()));
+        test.drone.set_tap(rng.gen::<bool>().then(|| rng.gen()));
+        test.drone.set_first_hop_correction(rng.gen());
+        let snapshot = test.drone.config();
+        let serialized = toml::to_string(&snapshot).unwrap();
+        assert_eq!(
+            toml::from_str::<DroneConfigSnapshot>(&serialized).unwrap(),
+            snapshot
+        );
+    }
+}
+
+#[test]
+fn config_snapshot_deserializer_survives_arbitrary_bytes() {
+    let mut rng = StdRng::seed_from_u64(236);
+    let valid = toml::to_string(&test_drone(1, &[0, 2]).drone.config()).unwrap();
+    for _ in 0..1024 {
+        let mut bytes = if rng.gen() {
+            valid.clone().into_bytes()
+        } else {
+            (0..rng.gen_range(0..256)).map(|_| rng.gen()).collect()
+        };
+        // Corrupt a few random bytes of the input.
+        for _ in 0..rng.gen_range(0..8) {
+            if !bytes.is_empty() {
+                let index = rng.gen_range(0..bytes.len());
+                bytes[index] = rng.gen();
+            }
+        }
+        // Any result is fine, as long as the deserializer does not panic.
+        let _ = toml::from_str::<DroneConfigSnapshot>(&String::from_utf8_lossy(&bytes));
+    }
+}
+
+#[test]
+fn burst_cycle_drops_and_passes_in_turn() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone
+        .set_drop_model(DropModel::BurstCycle { drop: 2, pass: 3 });
+    // The packet drop rate is ignored.
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.0));
+    for fragment_index in 0..10 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    let forwarded: Vec<u64> = take(&test.neighbors[&2])
+        .iter()
+        .map(Packet::get_fragment_index)
+        .collect();
+    assert_eq!(forwarded, vec![2, 3, 4, 7, 8, 9]);
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(
+        nacks,
+        [0, 1, 5, 6]
+            .into_iter()
+            .map(|fragment_index| (NackType::Dropped, fragment_index))
+            .collect::<Vec<_>>()
+    );
+
+    // Setting the model again restarts the cycle at its burst.
+    test.drone
+        .set_drop_model(DropModel::BurstCycle { drop: 2, pass: 3 });
+    test.drone.process_packet(fragment(7, 10, &[0, 1, 2], 1));
+    assert!(take(&test.neighbors[&2]).is_empty());
+}
+
+#[test]
+fn health_reports_each_adverse_condition() {
+    let mut test = test_drone(1, &[2]);
+    let health = test.drone.health();
+    assert_eq!(health.level, DroneHealth::Healthy);
+    assert!(health.reasons.is_empty());
+
+    // The only neighbor crashed: its channel is disconnected.
+    drop(test.neighbors.remove(&2));
+    test.drone.process_packet(flood_response(3, &[0, 1, 2], 1));
+    let health = test.drone.health();
+    assert_eq!(health.level, DroneHealth::Degraded);
+    assert_eq!(health.reasons, vec!["all neighbors crashed".to_string()]);
+
+    test.drone.process_command(DroneCommand::RemoveSender(2));
+    test.drone.command_channel_disconnected = true;
+    assert_eq!(
+        test.drone.health().reasons,
+        vec![
+            "no neighbors".to_string(),
+            "command channel disconnected".to_string()
+        ]
+    );
+
+    test.drone.process_command(DroneCommand::Crash);
+    let health = test.drone.health();
+    assert_eq!(health.level, DroneHealth::Dead);
+    assert!(!health.healthy);
+    assert_eq!(health.reasons.len(), 3);
+}
+
+#[test]
+fn commands_shuffled_after_a_crash_are_ignored() {
+    let mut ignored_add_sender = false;
+    for seed in 0..32 {
+        let mut test = test_drone(1, &[0]);
+        test.drone.set_rng_seed(seed);
+        test.drone.set_command_history_capacity(8);
+        test.drone.set_command_reorder_batch(Some(3));
+        let (sender, _receiver) = unbounded();
+        test.commands.send(DroneCommand::Crash).unwrap();
+        test.commands
+            .send(DroneCommand::AddSender(3, sender))
+            .unwrap();
+        test.drone
+            .process_reordered_commands(DroneCommand::SetPacketDropRate(0.5));
+
+        assert!(test.drone.is_crashed());
+        let history: Vec<CommandRecord> = test
+            .drone
+            .command_history()
+            .iter()
+            .map(|(_, record)| *record)
+            .collect();
+        assert_eq!(history.last(), Some(&CommandRecord::Crash));
+        let added = history.contains(&CommandRecord::AddSender(3));
+        assert_eq!(test.drone.packet_senders.contains_key(&3), added);
+        ignored_add_sender |= !added;
+    }
+    assert!(ignored_add_sender);
+}
+
+#[test]
+fn deterministic_drops_do_not_depend_on_the_order() {
+    let dropped_indexes = |order: Vec<u64>| {
+        let mut test = test_drone(1, &[0, 2]);
+        test.drone
+            .set_drop_model(DropModel::Deterministic { seed: 42 });
+        test.drone
+            .process_command(DroneCommand::SetPacketDropRate(0.5));
+        for fragment_index in order {
+            test.drone
+                .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+            // Unrelated traffic interleaved with the session.
+            test.drone
+                .process_packet(fragment(8, fragment_index, &[0, 1, 2], 1));
+        }
+        let mut dropped: Vec<u64> = take(&test.neighbors[&0])
+            .iter()
+            .filter(|packet| packet.session_id == 7)
+            .filter_map(nack_of)
+            .map(|(_, fragment_index)| fragment_index)
+            .collect();
+        dropped.sort_unstable();
+        dropped
+    };
+    let in_order = dropped_indexes((0..32).collect());
+    assert!(!in_order.is_empty() && in_order.len() < 32);
+    assert_eq!(dropped_indexes((0..32).rev().collect()), in_order);
+    assert_eq!(
+        dropped_indexes((0..32).map(|index| (index * 7) % 32).collect()),
+        in_order
+    );
+}
+
+#[test]
+fn flood_amplification_is_the_forwards_per_received_request() {
+    let mut test = test_drone(1, &[0, 2, 3, 4]);
+    assert_eq!(test.drone.stats().flood_amplification(), 0.0);
+    for flood_id in 0..4 {
+        test.drone.process_packet(flood(0, flood_id, &[0]).0);
+    }
+    assert_eq!(test.drone.stats().flood_requests_received, 4);
+    assert_eq!(test.drone.stats().flood_requests_forwarded, 12);
+    assert_eq!(test.drone.stats().flood_amplification(), 3.0);
+
+    // A flood already seen is answered, not forwarded.
+    test.drone.process_packet(flood(0, 0, &[0, 2]).0);
+    assert_eq!(test.drone.stats().flood_amplification(), 2.4);
+}
+
+#[test]
+fn flood_fanout_counts_the_neighbors_flooded() {
+    let mut test = test_drone(1, &[0, 2, 3, 4]);
+    test.drone.process_packet(flood(0, 1, &[0]).0);
+    test.drone.process_packet(flood(5, 1, &[5, 2]).0);
+    assert_eq!(test.drone.flood_fanout(0, 1), Some(3));
+    assert_eq!(test.drone.flood_fanout(5, 1), Some(3));
+    assert_eq!(test.drone.flood_fanout(0, 2), None);
+
+    // Sends to a crashed neighbor are not counted.
+    drop(test.neighbors.remove(&4));
+    test.drone.process_packet(flood(0, 2, &[0]).0);
+    assert_eq!(test.drone.flood_fanout(0, 2), Some(2));
+
+    // Only the last floods are tracked.
+    for flood_id in 100..100 + MAX_TRACKED_FLOOD_FANOUTS as u64 {
+        test.drone.process_packet(flood(0, flood_id, &[0]).0);
+    }
+    assert_eq!(test.drone.flood_fanout(0, 1), None);
+    assert_eq!(test.drone.flood_fanouts.len(), MAX_TRACKED_FLOOD_FANOUTS);
+}
+
+#[test]
+fn resolver_fills_the_gaps_of_incomplete_routes() {
+    let mut test = test_drone(1, &[0, 4, 5]);
+    test.drone.set_default_route(Some(4));
+    test.drone
+        .set_next_hop_resolver(Some(Box::new(|destination| match destination {
+            8 => Some(5),
+            9 => Some(6),
+            _ => None,
+        })));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 8], 1));
+    let resolved = take(&test.neighbors[&5]);
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].routing_header.hops, vec![0, 1, 5, 8]);
+    assert_eq!(resolved[0].routing_header.hop_index, 2);
+
+    // A destination resolved to a node that is not a neighbor falls back to the default route.
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 9], 1));
+    let rerouted = take(&test.neighbors[&4]);
+    assert_eq!(rerouted.len(), 1);
+    assert_eq!(rerouted[0].routing_header.hops, vec![0, 1, 4, 9]);
+
+    // Without the resolver nor the default route, the route is strict.
+    test.drone.set_next_hop_resolver(None);
+    test.drone.set_default_route(None);
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 8], 1));
+    assert!(take(&test.neighbors[&5]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::ErrorInRouting(8), 2)]);
+}
+
+#[test]
+fn send_attempts_are_reported_under_the_option() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    drop(test.neighbors.remove(&3));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 3], 1));
+    assert!(telemetry.try_recv().is_err());
+
+    test.drone.set_send_attempt_events(true);
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 3], 1));
+    let attempts: Vec<_> = telemetry
+        .try_iter()
+        .filter(|event| {
+            matches!(
+                event,
+                TelemetryEvent::SendAttempted { .. } | TelemetryEvent::SendCompleted { .. }
+            )
+        })
+        .collect();
+    assert_eq!(
+        attempts[..4],
+        [
+            TelemetryEvent::SendAttempted {
+                session_id: 7,
+                neighbor: 2,
+            },
+            TelemetryEvent::SendCompleted {
+                session_id: 7,
+                neighbor: 2,
+                success: true,
+            },
+            TelemetryEvent::SendAttempted {
+                session_id: 8,
+                neighbor: 3,
+            },
+            TelemetryEvent::SendCompleted {
+                session_id: 8,
+                neighbor: 3,
+                success: false,
+            },
+        ]
+    );
+    // The failed fragment is not reported as sent.
+    assert!(!test.events.try_iter().any(|event| matches!(
+        event,
+        DroneEvent::PacketSent(packet) if packet.session_id == 8 && nack_of(&packet).is_none()
+    )));
+}
+
+#[test]
+fn repeated_responses_to_an_adjacent_initiator_are_aggregated() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_initiator_response_dedup(true);
+    let response = |flood_id, trace: &[NodeId]| {
+        let mut hops: Vec<NodeId> = trace.to_vec();
+        hops.reverse();
+        Packet::new_flood_response(
+            SourceRoutingHeader {
+                hop_index: hops.iter().position(|id| *id == 1).unwrap(),
+                hops,
+            },
+            0,
+            FloodResponse {
+                flood_id,
+                path_trace: trace.iter().map(|id| (*id, NodeType::Drone)).collect(),
+            },
+        )
+    };
+    for trace in [[0, 1, 2, 5], [0, 1, 2, 5], [0, 1, 2, 6]] {
+        test.drone.process_packet(response(1, &trace));
+    }
+    test.drone.process_packet(response(2, &[0, 1, 2, 5]));
+    assert_eq!(take(&test.neighbors[&0]).len(), 3);
+
+    // Responses to an initiator further away are not aggregated.
+    for _ in 0..2 {
+        test.drone.process_packet(response(1, &[9, 0, 1, 2]));
+    }
+    assert_eq!(take(&test.neighbors[&0]).len(), 2);
+
+    // Only the last floods are tracked.
+    for flood_id in 100..100 + MAX_TRACKED_INITIATOR_FLOODS as u64 {
+        test.drone.process_packet(response(flood_id, &[0, 1, 2]));
+    }
+    assert_eq!(
+        test.drone.initiator_responses.len(),
+        MAX_TRACKED_INITIATOR_FLOODS
+    );
+    take(&test.neighbors[&0]);
+    test.drone.process_packet(response(1, &[0, 1, 2, 5]));
+    assert_eq!(take(&test.neighbors[&0]).len(), 1);
+}
+
+#[test]
+fn reverse_link_losses_hit_the_nacks_only() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_link_drop_rates(
+        0,
+        Some(LinkDropRates {
+            forward: 0.0,
+            reverse: 1.0,
+        }),
+    );
+    // The NACK of the unknown next hop goes back to 0 and is lost.
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 9], 1));
+    // A fragment forwarded to 0 goes along the link and arrives.
+    test.drone.process_packet(fragment(8, 0, &[2, 1, 0], 1));
+    let received = take(&test.neighbors[&0]);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].session_id, 8);
+    assert_eq!(test.drone.stats.link_losses, 1);
+    // The lost NACK was sent from the point of view of the drone.
+    assert_eq!(test.drone.stats.nacks_sent, 1);
+
+    test.drone.set_link_drop_rates(0, None);
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 9], 1));
+    assert_eq!(take(&test.neighbors[&0]).len(), 1);
+}
+
+#[test]
+fn link_drop_rates_apply_with_forward_workers() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    test.drone.set_forward_workers(2);
+    test.drone.spawn_forward_workers();
+    test.drone.set_link_drop_rates(
+        2,
+        Some(LinkDropRates {
+            forward: 1.0,
+            reverse: 0.0,
+        }),
+    );
+    for fragment_index in 0..4 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+        test.drone
+            .process_packet(fragment(8, fragment_index, &[0, 1, 3], 1));
+    }
+    assert_eq!(test.drone.stats.link_losses, 4);
+    assert!(take(&test.neighbors[&2]).is_empty());
+    // The other neighbors are still served by the workers.
+    for _ in 0..4 {
+        test.neighbors[&3]
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap();
+    }
+}
+
+#[test]
+fn min_gap_spaces_out_back_to_back_sends() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone.set_forward_workers(2);
+    test.drone.spawn_forward_workers();
+    test.drone
+        .set_neighbor_min_gap(2, Some(Duration::from_millis(250)));
+    for fragment_index in 0..3 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    // Every fragment counts as sent right away.
+    assert_eq!(
+        test.events
+            .try_iter()
+            .filter(|event| matches!(event, DroneEvent::PacketSent(_)))
+            .count(),
+        3
+    );
+
+    let mut sent_per_tick = Vec::new();
+    for _ in 0..6 {
+        sent_per_tick.push(
+            take(&test.neighbors[&2])
+                .iter()
+                .map(Packet::get_fragment_index)
+                .collect::<Vec<_>>(),
+        );
+        clock.advance(Duration::from_millis(100));
+        test.drone.send_due_deferred();
+    }
+    assert_eq!(
+        sent_per_tick,
+        vec![vec![0], vec![], vec![], vec![1], vec![], vec![2]]
+    );
+
+    // Neighbors without a gap are not delayed.
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 3], 1));
+    test.neighbors[&3]
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap();
+}
+
+#[test]
+fn dropped_fragment_is_retried_after_the_delay() {
+    let mut test = test_drone(1, &[0, 2]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone
+        .set_drop_retry_delay(Some(Duration::from_secs(1)));
+    test.drone
+        .set_drop_model(DropModel::BurstCycle { drop: 1, pass: 1 });
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert!(take(&test.neighbors[&2]).is_empty());
+    assert!(take(&test.neighbors[&0]).is_empty());
+    clock.advance(Duration::from_millis(900));
+    test.drone.send_due_retries();
+    assert!(take(&test.neighbors[&2]).is_empty());
+
+    clock.advance(Duration::from_millis(100));
+    test.drone.send_due_retries();
+    let forwarded = take(&test.neighbors[&2]);
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0].routing_header.hop_index, 2);
+    assert!(take(&test.neighbors[&0]).is_empty());
+
+    // A fragment dropped again on its retry is NACKed.
+    test.drone
+        .set_drop_model(DropModel::BurstCycle { drop: 2, pass: 1 });
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    clock.advance(Duration::from_secs(1));
+    test.drone.send_due_retries();
+    assert!(take(&test.neighbors[&2]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::Dropped, 1)]);
+    assert!(test.drone.pending_retries.is_empty());
+}
+
+#[test]
+fn flush_pending_settles_the_pending_retries() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_clock(Arc::new(ManualClock::new()));
+    test.drone
+        .set_drop_retry_delay(Some(Duration::from_secs(1)));
+    test.drone
+        .set_drop_model(DropModel::BurstCycle { drop: 1, pass: 1 });
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    test.drone.flush_pending();
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+    assert!(take(&test.neighbors[&0]).is_empty());
+
+    // With the dead-letter policy, the fragment waiting for its retry is NACKed instead.
+    test.drone
+        .set_pending_flush_policy(PendingFlushPolicy::DeadLetter);
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    test.drone.flush_pending();
+    assert!(take(&test.neighbors[&2]).is_empty());
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(nacks, vec![(NackType::Dropped, 1)]);
+    assert!(test.drone.pending_retries.is_empty());
+}
+
+#[test]
+fn role_summary_separates_transit_from_endpoint_traffic() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    test.drone
+        .set_neighbor_types(HashMap::from([(0, NodeType::Client)]));
+    test.drone.process_packet(fragment(7, 0, &[2, 1, 3], 1));
+    test.drone
+        .process_packet(fragment(7, 1, &[5, 3, 1, 2, 6], 2));
+    test.drone.process_packet(fragment(8, 0, &[0, 1, 2], 1));
+    // Neighbor 3 is learned to be a server from the trace of a flood.
+    let (packet, mut request) = flood(0, 1, &[0]);
+    request.path_trace = vec![(3, NodeType::Server)];
+    test.drone.process_packet(Packet {
+        pack_type: PacketType::FloodRequest(request),
+        ..packet
+    });
+    test.drone.process_packet(fragment(9, 0, &[2, 1, 3], 1));
+    assert_eq!(
+        test.drone.role_summary(),
+        RoleSummary {
+            transit: 2,
+            endpoint_adjacent: 2,
+        }
+    );
+    assert_eq!(test.drone.role_summary().transit_ratio(), 0.5);
+
+    // Only the last forwards are summarized.
+    for fragment_index in 0..ROLE_WINDOW as u64 {
+        test.drone
+            .process_packet(fragment(10, fragment_index, &[0, 1, 2], 1));
+    }
+    assert_eq!(
+        test.drone.role_summary(),
+        RoleSummary {
+            transit: 0,
+            endpoint_adjacent: ROLE_WINDOW as u64,
+        }
+    );
+}
+
+#[test]
+fn every_received_packet_is_counted() {
+    let mut test = test_drone(1, &[0, 2]);
+    let mut ack = fragment(7, 0, &[2, 1, 0], 1);
+    ack.pack_type = PacketType::Ack(Ack { fragment_index: 0 });
+    let packets = vec![
+        fragment(7, 0, &[0, 1, 2], 1),
+        fragment(7, 1, &[0, 1, 9], 1),
+        ack,
+        flood(0, 1, &[0]).0,
+        flood_response(1, &[2, 1, 0], 1),
+    ];
+    for packet in packets {
+        test.drone.process_packet(packet);
+    }
+    // A crashed drone still receives packets while it drains its channel.
+    test.drone.apply_command(DroneCommand::Crash);
+    test.drone.process_packet(fragment(7, 2, &[0, 1, 2], 1));
+
+    let stats = test.drone.stats();
+    assert_eq!(stats.packets_received, 6);
+    assert_eq!(stats.fragments_received, 3);
+    assert_eq!(stats.fragments_forwarded, 1);
+    assert_eq!(stats.control_data_ratio(), 1.0);
+}
+
+#[test]
+fn unreachable_destination_is_told_apart_from_a_missing_hop() {
+    let mut test = test_drone(1, &[0, 2]);
+    let (telemetry_sender, telemetry) = unbounded();
+    test.drone.set_telemetry_sender(Some(telemetry_sender));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 9], 1));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 8, 9], 1));
+    assert_eq!(test.drone.stats.unreachable_destinations, 1);
+    let unreachable: Vec<_> = telemetry
+        .try_iter()
+        .filter(|event| matches!(event, TelemetryEvent::DestinationUnreachable { .. }))
+        .collect();
+    assert_eq!(
+        unreachable,
+        vec![TelemetryEvent::DestinationUnreachable {
+            session_id: 7,
+            destination: 9,
+        }]
+    );
+    // Both fragments are NACKed the same way.
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    assert_eq!(
+        nacks,
+        vec![
+            (NackType::ErrorInRouting(9), 0),
+            (NackType::ErrorInRouting(8), 1)
+        ]
+    );
+}
+
+#[test]
+fn backlog_of_a_crash_is_drained_in_arrival_order() {
+    let test = test_drone(1, &[0, 2]);
+    let mut ack = fragment(7, 3, &[2, 1, 0], 1);
+    ack.pack_type = PacketType::Ack(Ack { fragment_index: 3 });
+    let backlog = vec![
+        fragment(7, 0, &[0, 1, 2], 1),
+        fragment(7, 1, &[0, 1, 2], 1),
+        ack,
+        fragment(7, 2, &[0, 1, 2], 1),
+        fragment(7, 4, &[0, 1, 2], 1),
+    ];
+    for packet in backlog {
+        test.packets.send(packet).unwrap();
+    }
+    // Commands are served first, so the whole backlog is drained after the crash.
+    test.commands.send(DroneCommand::Crash).unwrap();
+    let running = start(test);
+    let received = running.neighbors[&0].clone();
+    assert!(running.terminates());
+
+    let sent: Vec<_> = received
+        .try_iter()
+        .map(|packet| match packet.pack_type {
+            PacketType::Nack(nack) => (Some(nack.nack_type), nack.fragment_index),
+            PacketType::Ack(ack) => (None, ack.fragment_index),
+            _ => panic!("unexpected packet: {packet:?}"),
+        })
+        .collect();
+    let crashed = Some(NackType::ErrorInRouting(1));
+    assert_eq!(
+        sent,
+        vec![
+            (crashed, 0),
+            (crashed, 1),
+            (None, 3),
+            (crashed, 2),
+            (crashed, 4)
+        ]
+    );
+}
+
+#[test]
+fn adjacent_crashed_drones_terminate_once_their_senders_are_removed() {
+    let mut one = test_drone(1, &[]);
+    let mut two = test_drone(2, &[]);
+    one.drone
+        .process_command(DroneCommand::AddSender(2, two.packets.clone()));
+    two.drone
+        .process_command(DroneCommand::AddSender(1, one.packets.clone()));
+    let one = start(one);
+    let two = start(two);
+    one.commands.send(DroneCommand::Crash).unwrap();
+    two.commands.send(DroneCommand::Crash).unwrap();
+    let RunningDrone {
+        commands: one_commands,
+        packets,
+        done: one_done,
+        ..
+    } = one;
+    drop(packets);
+    let RunningDrone {
+        commands: two_commands,
+        packets,
+        done: two_done,
+        ..
+    } = two;
+    drop(packets);
+    // Each crashed drone keeps the other draining through its sender.
+    assert!(one_done.recv_timeout(Duration::from_millis(100)).is_err());
+    assert!(two_done.recv_timeout(Duration::from_millis(100)).is_err());
+
+    one_commands.send(DroneCommand::RemoveSender(2)).unwrap();
+    two_commands.send(DroneCommand::RemoveSender(1)).unwrap();
+    assert!(one_done.recv_timeout(Duration::from_secs(1)).is_ok());
+    assert!(two_done.recv_timeout(Duration::from_secs(1)).is_ok());
+}
+
+#[test]
+fn drone_terminates_once_both_channels_are_disconnected() {
+    capture_logs();
+    let RunningDrone {
+        commands,
+        packets,
+        done,
+        ..
+    } = start(test_drone(244, &[0]));
+    drop(packets);
+    assert!(done.recv_timeout(Duration::from_millis(100)).is_err());
+    drop(commands);
+    assert!(done.recv_timeout(Duration::from_secs(1)).is_ok());
+    assert!(logged(244)
+        .iter()
+        .any(|(_, level, message)| *level == Level::Warn
+            && message == "Drone 244 lost all its channels, terminating"));
+}
+
+#[test]
+fn tagged_events_carry_the_drone_id() {
+    let (tagged_sender, tagged) = unbounded();
+    let mut first = test_drone(1, &[0, 2]);
+    let mut second = test_drone(2, &[1, 3]);
+    first
+        .drone
+        .set_tagged_event_sender(Some(tagged_sender.clone()));
+    second.drone.set_tagged_event_sender(Some(tagged_sender));
+    first.drone.process_packet(fragment(7, 0, &[0, 1, 2, 3], 1));
+    second
+        .drone
+        .process_packet(fragment(7, 0, &[0, 1, 2, 3], 2));
+
+    let events: Vec<_> = tagged
+        .try_iter()
+        .map(|(id, event)| match event {
+            DroneEvent::PacketSent(packet) => (id, packet.routing_header.hop_index),
+            other => panic!("unexpected event: {other:?}"),
+        })
+        .collect();
+    assert_eq!(events, vec![(1, 2), (2, 3)]);
+    // The standard event channel still receives every event.
+    assert_eq!(first.events.try_iter().count(), 1);
+    assert_eq!(second.events.try_iter().count(), 1);
+}
+
+#[test]
+fn invalid_control_packets_follow_the_policy() {
+    let mut test = test_drone(1, &[0, 2]);
+    let invalid_ack = || {
+        let mut ack = fragment(7, 0, &[0, 3, 2], 1);
+        ack.pack_type = PacketType::Ack(Ack { fragment_index: 0 });
+        ack
+    };
+    test.drone.process_packet(invalid_ack());
+    assert!(matches!(
+        test.events.try_recv(),
+        Ok(DroneEvent::ControllerShortcut(_))
+    ));
+
+    test.drone
+        .set_invalid_control_policy(InvalidControlPolicy::Discard);
+    test.drone.process_packet(invalid_ack());
+    assert!(test.events.try_recv().is_err());
+    assert!(take(&test.neighbors[&0]).is_empty());
+    assert!(take(&test.neighbors[&2]).is_empty());
+}
+
+#[test]
+fn topology_fingerprint_follows_the_neighbor_set() {
+    let mut test = test_drone(1, &[0, 2, 3]);
+    let initial = test.drone.topology_fingerprint();
+    assert_eq!(
+        test_drone(1, &[3, 0, 2]).drone.topology_fingerprint(),
+        initial
+    );
+    // Only the neighbor set matters, not the drone.
+    assert_eq!(
+        test_drone(5, &[2, 3, 0]).drone.topology_fingerprint(),
+        initial
+    );
+
+    let (sender, _receiver) = unbounded();
+    test.drone
+        .process_command(DroneCommand::AddSender(4, sender));
+    let added = test.drone.topology_fingerprint();
+    assert_ne!(added, initial);
+    test.drone.process_command(DroneCommand::RemoveSender(4));
+    assert_eq!(test.drone.topology_fingerprint(), initial);
+    test.drone.process_command(DroneCommand::RemoveSender(3));
+    assert_ne!(test.drone.topology_fingerprint(), initial);
+}
+
+#[test]
+fn path_tracing_does_not_alter_the_forwarded_packets() {
+    let mut traced = test_drone(1, &[0, 2]);
+    let mut untraced = test_drone(1, &[0, 2]);
+    traced
+        .drone
+        .set_path_trace_map(Some(PathTraceMap::default()));
+    for test in [&mut traced, &mut untraced] {
+        test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    }
+    let traced_packet = take(&traced.neighbors[&2]).remove(0);
+    let untraced_packet = take(&untraced.neighbors[&2]).remove(0);
+    assert_eq!(format!("{traced_packet:?}"), format!("{untraced_packet:?}"));
+}
+
+#[test]
+fn high_drop_rate_warning_fires_once_per_window() {
+    capture_logs();
+    let mut test = test_drone(247, &[0, 2]);
+    let clock = Arc::new(ManualClock::new());
+    test.drone.set_clock(clock.clone());
+    test.drone
+        .set_drop_rate_warning(Some(0.5), Duration::from_secs(10));
+    let route = [0, 247, 2];
+    for fragment_index in 0..5 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &route, 1));
+    }
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(1.0));
+    for fragment_index in 5..20 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &route, 1));
+    }
+    clock.advance(Duration::from_secs(10));
+    for fragment_index in 20..30 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &route, 1));
+    }
+
+    let warnings: Vec<_> = logged(247)
+        .into_iter()
+        .filter(|(_, level, message)| *level == Level::Warn && message.contains("threshold"))
+        .map(|(_, _, message)| message)
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "Drone 247 dropped 55% of its last 11 fragments (threshold 50%)".to_string(),
+            "Drone 247 dropped 100% of its last 10 fragments (threshold 50%)".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn traced_neighbors_are_skipped_under_the_option() {
+    let mut test = test_drone(1, &[0, 2, 3, 4]);
+    test.drone.process_packet(flood(0, 1, &[0, 3, 2]).0);
+    for (neighbor, expected) in [(0, 1), (2, 0), (3, 1), (4, 1)] {
+        assert_eq!(take(&test.neighbors[&neighbor]).len(), expected);
+    }
+
+    test.drone.set_skip_traced_flood_neighbors(true);
+    test.drone.process_packet(flood(0, 2, &[0, 3, 2]).0);
+    for (neighbor, expected) in [(0, 0), (2, 0), (3, 0), (4, 1)] {
+        assert_eq!(take(&test.neighbors[&neighbor]).len(), expected);
+    }
+    assert_eq!(test.drone.flood_fanout(0, 2), Some(1));
+}
+
+#[test]
+fn held_fragments_are_forwarded_on_release() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_hold_capacity(2);
+    test.drone
+        .process_extension_command(GetDronedCommand::Hold(true));
+    for fragment_index in 0..3 {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+    }
+    assert_eq!(test.drone.stats.hold_overflows, 1);
+    // Commands and control packets are still processed while held.
+    test.drone
+        .process_command(DroneCommand::SetPacketDropRate(0.0));
+    test.drone.process_packet(flood_response(1, &[0, 1, 2], 1));
+    let sent = take(&test.neighbors[&2]);
+    assert_eq!(sent.len(), 1);
+    assert!(nack_of(&sent[0]).is_none());
+    assert!(matches!(sent[0].pack_type, PacketType::FloodResponse(_)));
+
+    test.drone
+        .process_extension_command(GetDronedCommand::Hold(false));
+    assert_eq!(
+        take(&test.neighbors[&2])
+            .iter()
+            .map(Packet::get_fragment_index)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    assert!(test.drone.held_fragments.is_empty());
+    test.drone.process_packet(fragment(7, 3, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn held_drone_settles_its_fragments_when_it_terminates() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.set_held(true);
+    for fragment_index in 0..3 {
+        test.packets
+            .send(fragment(7, fragment_index, &[0, 1, 2], 1))
+            .unwrap();
+    }
+    let running = start(test);
+    let forwarded = running.neighbors[&2].clone();
+    assert!(running.terminates());
+    assert_eq!(
+        forwarded
+            .try_iter()
+            .map(|packet| packet.get_fragment_index())
+            .collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+}
+
+#[test]
+fn each_command_variant_is_applied_directly() {
+    let mut test = test_drone(1, &[0]);
+    let (sender, receiver) = unbounded();
+    test.drone.apply_command(DroneCommand::AddSender(2, sender));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert_eq!(take(&receiver).len(), 1);
+
+    test.drone
+        .apply_command(DroneCommand::SetPacketDropRate(1.0));
+    test.drone.process_packet(fragment(7, 1, &[0, 1, 2], 1));
+    assert!(take(&receiver).is_empty());
+    assert_eq!(test.drone.stats.fragments_dropped, 1);
+
+    test.drone.apply_command(DroneCommand::RemoveSender(2));
+    assert_eq!(test.drone.neighbor_status(2), NeighborStatus::Unknown);
+
+    test.drone.apply_command(DroneCommand::Crash);
+    assert!(test.drone.is_crashed());
+}
+
+#[test]
+fn fragments_of_a_session_are_forwarded_in_the_configured_order() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone
+        .set_fragment_order(FragmentOrder::Reverse { batch: 3 });
+    for fragment_index in [0, 1, 2, 3, 4, 99] {
+        test.drone
+            .process_packet(fragment(7, fragment_index, &[0, 1, 2], 1));
+        // Another session is buffered separately.
+        test.drone
+            .process_packet(fragment(8, fragment_index, &[0, 1, 2], 1));
+    }
+    let forwarded: Vec<(u64, u64)> = take(&test.neighbors[&2])
+        .iter()
+        .map(|packet| (packet.session_id, packet.get_fragment_index()))
+        .collect();
+    let session = |session_id| {
+        forwarded
+            .iter()
+            .filter(|(id, _)| *id == session_id)
+            .map(|(_, fragment_index)| *fragment_index)
+            .collect::<Vec<_>>()
+    };
+    // The last fragment of the session forwards its incomplete batch.
+    assert_eq!(session(7), vec![2, 1, 0, 99, 4, 3]);
+    assert_eq!(session(8), vec![2, 1, 0, 99, 4, 3]);
+
+    test.drone.set_rng_seed(3);
+    test.drone
+        .set_fragment_order(FragmentOrder::Shuffled { batch: 8 });
+    for fragment_index in 0..8 {
+        test.drone
+            .process_packet(fragment(9, fragment_index, &[0, 1, 2], 1));
+    }
+    let mut shuffled: Vec<u64> = take(&test.neighbors[&2])
+        .iter()
+        .map(Packet::get_fragment_index)
+        .collect();
+    assert_ne!(shuffled, (0..8).collect::<Vec<_>>());
+    shuffled.sort_unstable();
+    assert_eq!(shuffled, (0..8).collect::<Vec<_>>());
+
+    // Going back in order forwards the buffered fragments.
+    test.drone.process_packet(fragment(9, 8, &[0, 1, 2], 1));
+    assert!(take(&test.neighbors[&2]).is_empty());
+    test.drone.set_fragment_order(FragmentOrder::InOrder);
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn imported_floods_are_answered_instead_of_forwarded() {
+    let mut test = test_drone(1, &[0, 2]);
+    for (initiator, flood_id) in [(0, 2), (0, 1), (3, 1)] {
+        test.drone
+            .process_packet(flood(initiator, flood_id, &[initiator, 0]).0);
+    }
+    let exported = test.drone.export_received_floods();
+    assert_eq!(exported, vec![(0, 1), (0, 2), (3, 1)]);
+    take(&test.neighbors[&2]);
+
+    test.drone.clear_received_floods();
+    assert!(test.drone.export_received_floods().is_empty());
+    test.drone.import_received_floods(&exported);
+    assert_eq!(test.drone.export_received_floods(), exported);
+    let (packet, request) = flood(0, 1, &[0]);
+    assert_eq!(
+        test.drone.process_flood_request(packet, request),
+        ForwardOutcome::FloodResponded
+    );
+    assert!(take(&test.neighbors[&2]).is_empty());
+
+    // Without the import, the flood is forwarded again.
+    test.drone.clear_received_floods();
+    test.drone.process_packet(flood(0, 1, &[0]).0);
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+}
+
+#[test]
+fn reserved_session_follows_the_policy() {
+    let mut test = test_drone(1, &[0, 2]);
+    test.drone.process_packet(fragment(0, 0, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+    assert_eq!(test.drone.stats.reserved_sessions, 0);
+
+    test.drone
+        .set_reserved_session_policy(ReservedSessionPolicy::Flag);
+    test.drone.process_packet(fragment(0, 1, &[0, 1, 2], 1));
+    assert_eq!(take(&test.neighbors[&2]).len(), 1);
+    assert_eq!(test.drone.stats.reserved_sessions, 1);
+
+    test.drone
+        .set_reserved_session_policy(ReservedSessionPolicy::Reject);
+    test.drone.process_packet(fragment(0, 2, &[0, 1, 2], 1));
+    test.drone.process_packet(flood_response(1, &[2, 1, 0], 1));
+    test.drone.process_packet(fragment(7, 0, &[0, 1, 2], 1));
+    assert_eq!(test.drone.stats.reserved_sessions, 3);
+    let forwarded = take(&test.neighbors[&2]);
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0].session_id, 7);
+    let nacks: Vec<_> = take(&test.neighbors[&0])
+        .iter()
+        .filter_map(nack_of)
+        .collect();
+    // The flood response is discarded.
+    assert_eq!(nacks, vec![(NackType::Dropped, 2)]);
+    assert_eq!(test.drone.session_loss(0), Some(1));
+}
+
+#[test]
+fn logger_initializers_are_idempotent() {
+    // The test logger is installed first, as an embedder would.
+    capture_logs();
+    assert!(init_logger().is_ok());
+    assert!(init_logger().is_ok());
+    assert!(init_logger_for_drone(251).is_ok());
+}
+
+#[test]
+fn logger_level_is_validated() {
+    capture_logs();
+    assert!(init_logger_with_level("debug").is_ok());
+    assert!(init_logger_with_level("info, drone::flood=debug").is_ok());
+    assert!(init_logger_with_level("drone=verbose").is_err());
+}
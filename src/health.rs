@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Structured health of a drone, returned by `GetDroned::health`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    /// Whether no adverse condition is detected.
+    pub healthy: bool,
+    /// Overall level of the health.
+    pub level: DroneHealth,
+    /// Why the drone is unhealthy, one entry per adverse condition (empty if healthy).
+    pub reasons: Vec<String>,
+}
+
+/// Overall level of the health of a drone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DroneHealth {
+    /// No adverse condition.
+    Healthy,
+    /// The drone works, but with adverse conditions: isolated, all its neighbors crashed, its
+    /// command channel disconnected, or a health threshold exceeded.
+    Degraded,
+    /// The drone crashed.
+    Dead,
+}
+
+/// Thresholds above which a drone reports itself unhealthy.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HealthThresholds {
+    /// Maximum ratio of dropped fragments over the fragments forwarded or dropped (0.00 to 1.00).
+    pub max_drop_ratio: f64,
+    /// Maximum time without receiving any packet (no limit if `None`).
+    pub max_idle: Option<Duration>,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            max_drop_ratio: 0.5,
+            max_idle: None,
+        }
+    }
+}
@@ -1,4 +1,18 @@
 // lib.rs
 
+pub mod builder;
+pub mod clock;
+pub mod command;
+pub mod config;
 pub mod get_droned;
+pub mod health;
+pub mod neighbor;
+pub mod outcome;
+pub mod policy;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod recording;
+pub mod stats;
+pub mod telemetry;
+pub mod trace;
+pub use builder::GetDronedBuilder;
 pub use get_droned::GetDroned;
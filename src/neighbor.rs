@@ -0,0 +1,12 @@
+/// How the drone currently treats the link to a node, as reported by `GetDroned::neighbor_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighborStatus {
+    /// The node is not a neighbor of the drone.
+    Unknown,
+    /// The node is a neighbor and the last send to it, if any, succeeded.
+    Alive,
+    /// The node is still a neighbor, but the last send to it failed because its channel is
+    /// disconnected, which happens when it crashed. The drone keeps trying it until the
+    /// controller removes it, and considers it alive again as soon as a send succeeds.
+    KnownCrashed,
+}
@@ -0,0 +1,34 @@
+use wg_2024::network::NodeId;
+use wg_2024::packet::NackType;
+
+/// Decision taken by the drone for a processed packet.
+///
+/// The outcomes are recorded in a bounded history when it is enabled
+/// (see `GetDroned::set_outcome_history_capacity`), so that tests can check the sequence of
+/// forwarding decisions without listening to the events of the drone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForwardOutcome {
+    /// The packet was sent to the given neighbor (the gateway if it was rerouted).
+    Forwarded(NodeId),
+    /// The fragment was dropped according to the packet drop rate, and NACKed.
+    Dropped,
+    /// The packet was refused and NACKed with the given NACK type.
+    Nacked(NackType),
+    /// The packet could not be forwarded and was sent to the controller.
+    Shortcut,
+    /// The packet could not be forwarded and was discarded.
+    Discarded,
+    /// The flood request was forwarded to the given number of neighbors.
+    FloodForwarded(usize),
+    /// The flood request will be forwarded after its jitter delay.
+    FloodDeferred,
+    /// The flood request was answered with a flood response.
+    FloodResponded,
+    /// The fragment was drawn as dropped and will be retried once after the retry delay.
+    RetryDeferred,
+    /// The fragment was buffered because the drone is held, and will be processed on release.
+    Held,
+    /// The fragment was buffered, to be forwarded with the next fragments of its session in the
+    /// configured fragment order.
+    Buffered,
+}
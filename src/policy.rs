@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+/// Decides when a drone answers a flood request itself instead of forwarding it,
+/// depending on its neighbors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LeafFloodPolicy {
+    /// Respond when the drone has no neighbor other than the sender of the request.
+    #[default]
+    RespondIfNoOtherNeighbor,
+    /// Respond whenever the drone has exactly one neighbor, even if it is not the sender.
+    RespondAtDegreeOne,
+    /// Never short-circuit: a leaf forwards the request to its other neighbors, if any.
+    AlwaysForward,
+}
+
+/// Decides which fragments a drone drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DropModel {
+    /// Each fragment is dropped independently with the packet drop rate, using the drone's RNG.
+    #[default]
+    Bernoulli,
+    /// Deterministic burst loss ignoring the packet drop rate: `drop` consecutive fragments are
+    /// dropped, then `pass` are forwarded, cycling.
+    BurstCycle {
+        /// Number of consecutive fragments dropped at the start of each cycle.
+        drop: u32,
+        /// Number of consecutive fragments forwarded after the burst.
+        pass: u32,
+    },
+    /// Each fragment is dropped with the packet drop rate, decided by a hash of its session ID,
+    /// its fragment index, the drone ID and `seed` instead of an RNG draw: a given fragment gets
+    /// the same decision whatever the order in which the fragments are processed.
+    Deterministic {
+        /// Seed mixed into the hash of each fragment.
+        seed: u64,
+    },
+}
+
+/// Order in which a drone forwards the fragments of a session, to exercise the reassembly of
+/// the destination. Except in order, the fragments of each session are buffered until `batch`
+/// of them are available, or the last fragment of the session is received, then forwarded
+/// together in the chosen order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FragmentOrder {
+    /// Forward each fragment immediately.
+    #[default]
+    InOrder,
+    /// Forward each batch in reverse arrival order.
+    Reverse {
+        /// Number of fragments of a session buffered before they are forwarded.
+        batch: usize,
+    },
+    /// Forward each batch in a random order, drawn with the drone's RNG.
+    Shuffled {
+        /// Number of fragments of a session buffered before they are forwarded.
+        batch: usize,
+    },
+}
+
+/// Decides what a drone does with a packet of the session 0, which some rulesets reserve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ReservedSessionPolicy {
+    /// Process the packet like any other.
+    #[default]
+    Accept,
+    /// Log and count the packet, then process it like any other.
+    Flag,
+    /// Log and count the packet, then refuse it: a fragment is NACKed with `Dropped`, and any
+    /// other packet is discarded.
+    Reject,
+}
+
+/// Decides what a held drone (see `GetDroned::set_held`) does with the flood requests it receives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HeldFloodPolicy {
+    /// Process the request like a running drone: forward it, or respond to it.
+    #[default]
+    Forward,
+    /// Respond to the request immediately, as a leaf, without forwarding it.
+    Respond,
+    /// Discard the request, as if it were lost.
+    Ignore,
+    /// NACK the request with `Dropped` back along its path trace, without forwarding it.
+    Nack,
+}
+
+/// Decides what happens to the packets still pending in a drone (held, reordered, deferred or
+/// waiting for a retry) when they are flushed, e.g. when the drone terminates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PendingFlushPolicy {
+    /// Send the packets immediately, as if their time had come.
+    #[default]
+    Send,
+    /// Drop the packets and report each of them to the controller with a `PacketDropped` event;
+    /// a dropped fragment is also NACKed with `Dropped`, so that its source can resend it.
+    DeadLetter,
+}
+
+/// Decides what a drone does with an ACK, a NACK or a flood response whose routing header is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum InvalidControlPolicy {
+    /// Send the packet to the controller with a `ControllerShortcut` event.
+    #[default]
+    Shortcut,
+    /// Log the packet and discard it: an invalid control packet may be garbage, not worth
+    /// handing to the controller.
+    Discard,
+}
+
+/// Probabilities of losing a packet on the link to a neighbor, depending on its direction,
+/// for asymmetric link experiments.
+///
+/// Fragments and flood requests travel forward (away from the source of the session or the
+/// initiator of the flood); ACKs, NACKs and flood responses travel in reverse.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct LinkDropRates {
+    /// Probability of losing a fragment or a flood request sent on the link (0.00 to 1.00).
+    pub forward: f32,
+    /// Probability of losing an ACK, a NACK or a flood response sent on the link (0.00 to 1.00).
+    pub reverse: f32,
+}
@@ -0,0 +1,87 @@
+use crate::outcome::ForwardOutcome;
+use wg_2024::network::NodeId;
+use wg_2024::packet::NackType;
+
+/// Side effect of the drone, captured in order by the recording mode
+/// (see `GetDroned::start_recording`). Only available in tests or with the `test-utils` feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelineEntry {
+    /// A packet of the session was sent on the channel of the given neighbor.
+    Sent {
+        /// Neighbor the packet was sent to.
+        to: NodeId,
+        /// Session of the packet.
+        session_id: u64,
+    },
+    /// A NACK was generated for a packet of the session (before it is sent).
+    Nack {
+        /// Session of the NACKed packet.
+        session_id: u64,
+        /// Type of the NACK.
+        nack_type: NackType,
+    },
+    /// A `DroneEvent::PacketSent` was emitted for a packet of the session.
+    PacketSentEvent(u64),
+    /// A `DroneEvent::PacketDropped` was emitted for a packet of the session.
+    PacketDroppedEvent(u64),
+    /// A `DroneEvent::ControllerShortcut` was emitted for a packet of the session.
+    ControllerShortcutEvent(u64),
+    /// The processing of a received packet ended with the given outcome.
+    Outcome(ForwardOutcome),
+}
+
+/// Asserts that a recorded timeline matches the expected one, entry by entry.
+///
+/// # Parameters
+/// - `actual`: The timeline taken from the drone with `GetDroned::take_timeline`.
+/// - `expected`: The expected entries, in order.
+///
+/// # Panics
+/// If the timelines differ, naming the first differing position and printing both timelines.
+pub fn assert_timeline(actual: &[TimelineEntry], expected: &[TimelineEntry]) {
+    let mismatch = actual
+        .iter()
+        .zip(expected)
+        .position(|(actual, expected)| actual != expected)
+        .or((actual.len() != expected.len()).then(|| actual.len().min(expected.len())));
+    if let Some(index) = mismatch {
+        panic!(
+            "timelines differ at entry {}\n  actual: {:#?}\nexpected: {:#?}",
+            index, actual, expected
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMELINE: [TimelineEntry; 2] = [
+        TimelineEntry::Sent {
+            to: 2,
+            session_id: 7,
+        },
+        TimelineEntry::PacketSentEvent(7),
+    ];
+
+    #[test]
+    fn identical_timelines_match() {
+        assert_timeline(&TIMELINE, &TIMELINE);
+        assert_timeline(&[], &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "timelines differ at entry 1")]
+    fn differing_entry_is_named() {
+        assert_timeline(
+            &TIMELINE,
+            &[TIMELINE[0], TimelineEntry::PacketDroppedEvent(7)],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "timelines differ at entry 2")]
+    fn missing_entry_is_named() {
+        assert_timeline(&TIMELINE, &[TIMELINE[0], TIMELINE[1], TIMELINE[1]]);
+    }
+}
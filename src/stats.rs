@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Display;
+
+/// Version of the binary format of `DroneStats`, written as its first byte.
+pub const STATS_FORMAT_VERSION: u8 = 1;
+
+/// Number of counters in the binary format of `DroneStats`.
+/// Adding a counter changes the format: `STATS_FORMAT_VERSION` must be bumped once in the
+/// release that adds it.
+const STATS_COUNTER_COUNT: usize = 19;
+
+/// Counters describing the activity of a drone since it was created.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DroneStats {
+    /// Fragments forwarded to the next hop.
+    pub fragments_forwarded: u64,
+    /// Sum of the route lengths (`hops.len()`) of the forwarded fragments.
+    pub forwarded_path_length_total: u64,
+    /// Fragments dropped according to the packet drop rate.
+    pub fragments_dropped: u64,
+    /// NACKs sent by the drone.
+    pub nacks_sent: u64,
+    /// Flood responses generated by the drone itself (leaf or already received flood).
+    pub floods_responded: u64,
+    /// Flood responses forwarded by the drone on their way back to the initiator.
+    pub flood_responses_forwarded: u64,
+    /// Fragments refused because their session would exceed the maximum number of active sessions.
+    pub sessions_refused: u64,
+    /// Fragments dropped because their payload exceeded the maximum fragment size.
+    pub oversized_fragments: u64,
+    /// Packets rejected because their routing header exceeded the maximum number of hops.
+    pub overlong_routes: u64,
+    /// NACKs not sent because of the NACK rate limit.
+    pub nacks_suppressed: u64,
+    /// Received NACKs originated by the drone itself or reporting the drone as the failing node.
+    pub self_referential_nacks: u64,
+    /// Flood requests received by the drone, including the copies of already received floods.
+    pub flood_requests_received: u64,
+    /// Copies of flood requests forwarded to the neighbors of the drone.
+    pub flood_requests_forwarded: u64,
+    /// Packets lost on the link to a neighbor according to its directional drop rates.
+    pub link_losses: u64,
+    /// Packets received by the drone, of every type.
+    pub packets_received: u64,
+    /// Fragments NACKed with `ErrorInRouting` because their next hop is their destination,
+    /// which is not a neighbor (a subset of the routing errors, told apart from a missing
+    /// intermediate hop).
+    pub unreachable_destinations: u64,
+    /// Fragments dropped because the hold buffer was full (also counted in `fragments_dropped`).
+    pub hold_overflows: u64,
+    /// Packets of the reserved session 0 flagged or rejected by the reserved session policy.
+    pub reserved_sessions: u64,
+    /// Fragments received by the drone (the data traffic among `packets_received`).
+    pub fragments_received: u64,
+}
+
+impl DroneStats {
+    /// Returns the average route length (`hops.len()`) of the forwarded fragments,
+    /// or 0 if no fragment was forwarded.
+    pub fn average_path_length(&self) -> f64 {
+        if self.fragments_forwarded == 0 {
+            return 0.0;
+        }
+        self.forwarded_path_length_total as f64 / self.fragments_forwarded as f64
+    }
+
+    /// Returns the flood amplification factor of the drone: the number of flood request copies
+    /// it forwarded per flood request it received, or 0 if no flood request was received.
+    /// A factor close to the number of neighbors means that the drone floods almost every request.
+    pub fn flood_amplification(&self) -> f64 {
+        if self.flood_requests_received == 0 {
+            return 0.0;
+        }
+        self.flood_requests_forwarded as f64 / self.flood_requests_received as f64
+    }
+
+    /// Returns the ratio of control traffic (ACKs, NACKs, flood requests and responses) to data
+    /// traffic (fragments) received by the drone, a quick measure of the protocol overhead
+    /// through it, or 0 if no fragment was received.
+    pub fn control_data_ratio(&self) -> f32 {
+        if self.fragments_received == 0 {
+            return 0.0;
+        }
+        (self.packets_received - self.fragments_received) as f32 / self.fragments_received as f32
+    }
+
+    /// Encodes the counters in a compact binary format: a version byte (`STATS_FORMAT_VERSION`)
+    /// followed by each counter as a little-endian `u64`, in declaration order.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + STATS_COUNTER_COUNT * 8);
+        bytes.push(STATS_FORMAT_VERSION);
+        for counter in self.counters() {
+            bytes.extend_from_slice(&counter.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes counters encoded with `to_bytes`.
+    ///
+    /// # Parameters
+    /// - `bytes`: The encoded counters.
+    ///
+    /// # Returns
+    /// - `Ok(DroneStats)` with the decoded counters.
+    /// - `Err(StatsFormatError)` if the version is not supported or the length does not match it.
+    pub fn from_bytes(bytes: &[u8]) -> Result<DroneStats, StatsFormatError> {
+        let (&version, payload) = bytes
+            .split_first()
+            .ok_or(StatsFormatError::InvalidLength(0))?;
+        if version != STATS_FORMAT_VERSION {
+            return Err(StatsFormatError::UnsupportedVersion(version));
+        }
+        if payload.len() != STATS_COUNTER_COUNT * 8 {
+            return Err(StatsFormatError::InvalidLength(bytes.len()));
+        }
+        let mut counters = payload
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()));
+        let mut next = || counters.next().unwrap();
+        Ok(DroneStats {
+            fragments_forwarded: next(),
+            forwarded_path_length_total: next(),
+            fragments_dropped: next(),
+            nacks_sent: next(),
+            floods_responded: next(),
+            flood_responses_forwarded: next(),
+            sessions_refused: next(),
+            oversized_fragments: next(),
+            overlong_routes: next(),
+            nacks_suppressed: next(),
+            self_referential_nacks: next(),
+            flood_requests_received: next(),
+            flood_requests_forwarded: next(),
+            link_losses: next(),
+            packets_received: next(),
+            unreachable_destinations: next(),
+            hold_overflows: next(),
+            reserved_sessions: next(),
+            fragments_received: next(),
+        })
+    }
+
+    /// Returns the counters in declaration order, as encoded by `to_bytes`.
+    fn counters(&self) -> [u64; STATS_COUNTER_COUNT] {
+        [
+            self.fragments_forwarded,
+            self.forwarded_path_length_total,
+            self.fragments_dropped,
+            self.nacks_sent,
+            self.floods_responded,
+            self.flood_responses_forwarded,
+            self.sessions_refused,
+            self.oversized_fragments,
+            self.overlong_routes,
+            self.nacks_suppressed,
+            self.self_referential_nacks,
+            self.flood_requests_received,
+            self.flood_requests_forwarded,
+            self.link_losses,
+            self.packets_received,
+            self.unreachable_destinations,
+            self.hold_overflows,
+            self.reserved_sessions,
+            self.fragments_received,
+        ]
+    }
+}
+
+/// Error returned when decoding `DroneStats` from the binary format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsFormatError {
+    /// The version byte is not `STATS_FORMAT_VERSION`.
+    UnsupportedVersion(u8),
+    /// The encoded stats have the given length, which does not match the format.
+    InvalidLength(usize),
+}
+
+impl Display for StatsFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatsFormatError::UnsupportedVersion(version) => {
+                write!(f, "unsupported stats format version {}", version)
+            }
+            StatsFormatError::InvalidLength(length) => {
+                write!(f, "invalid stats length {}", length)
+            }
+        }
+    }
+}
+
+impl Error for StatsFormatError {}
+
+/// Histogram of the route lengths (`hops.len()`) of the packets processed by a drone,
+/// returned by `GetDroned::route_length_histogram`. Flood requests, which have no route, are not counted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteLengthHistogram {
+    /// Packets per bucket of route lengths: up to 2 hops, 3 to 4, 5 to 8, 9 to 16, and 17 or more.
+    pub counts: [u64; 5],
+}
+
+impl RouteLengthHistogram {
+    /// Inclusive upper bounds of the buckets, except the last one which has no bound.
+    pub const BUCKET_BOUNDS: [usize; 4] = [2, 4, 8, 16];
+
+    /// Counts a route of the given length in its bucket.
+    pub(crate) fn record(&mut self, length: usize) {
+        let bucket = Self::BUCKET_BOUNDS
+            .iter()
+            .position(|bound| length <= *bound)
+            .unwrap_or(Self::BUCKET_BOUNDS.len());
+        self.counts[bucket] += 1;
+    }
+}
+
+/// Role of a drone in its recent traffic, returned by `GetDroned::role_summary`: how many of
+/// its last forwarded fragments went between two drones, and how many came from or went to a
+/// client or a server.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoleSummary {
+    /// Fragments received from a drone and forwarded to a drone.
+    pub transit: u64,
+    /// Fragments received from or forwarded to a client or a server.
+    pub endpoint_adjacent: u64,
+}
+
+impl RoleSummary {
+    /// Returns the share of transit forwards among the summarized forwards,
+    /// or 0 if there are none.
+    pub fn transit_ratio(&self) -> f64 {
+        let total = self.transit + self.endpoint_adjacent;
+        if total == 0 {
+            return 0.0;
+        }
+        self.transit as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn average_path_length_of_forwarded_fragments() {
+        assert_eq!(DroneStats::default().average_path_length(), 0.0);
+        let stats = DroneStats {
+            fragments_forwarded: 4,
+            forwarded_path_length_total: 14,
+            ..DroneStats::default()
+        };
+        assert_eq!(stats.average_path_length(), 3.5);
+    }
+
+    #[test]
+    fn binary_format_round_trips() {
+        let stats = DroneStats {
+            fragments_forwarded: 1,
+            nacks_sent: 2,
+            link_losses: u64::MAX,
+            fragments_received: 4,
+            ..DroneStats::default()
+        };
+        let bytes = stats.to_bytes();
+        assert_eq!(bytes.len(), 1 + STATS_COUNTER_COUNT * 8);
+        assert_eq!(bytes[0], STATS_FORMAT_VERSION);
+        assert_eq!(DroneStats::from_bytes(&bytes), Ok(stats));
+    }
+
+    #[test]
+    fn binary_format_rejects_other_versions_and_lengths() {
+        let mut bytes = DroneStats::default().to_bytes();
+        assert_eq!(
+            DroneStats::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(StatsFormatError::InvalidLength(bytes.len() - 1))
+        );
+        assert_eq!(
+            DroneStats::from_bytes(&[]),
+            Err(StatsFormatError::InvalidLength(0))
+        );
+        bytes[0] = STATS_FORMAT_VERSION + 1;
+        assert_eq!(
+            DroneStats::from_bytes(&bytes),
+            Err(StatsFormatError::UnsupportedVersion(
+                STATS_FORMAT_VERSION + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn route_lengths_are_counted_in_their_bucket() {
+        let mut histogram = RouteLengthHistogram::default();
+        for length in [0, 2, 3, 4, 5, 8, 9, 16, 17, 200] {
+            histogram.record(length);
+        }
+        assert_eq!(histogram.counts, [2, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn control_data_ratio_of_received_packets() {
+        assert_eq!(DroneStats::default().control_data_ratio(), 0.0);
+        let control_only = DroneStats {
+            packets_received: 3,
+            ..DroneStats::default()
+        };
+        assert_eq!(control_only.control_data_ratio(), 0.0);
+        let stats = DroneStats {
+            packets_received: 10,
+            fragments_received: 4,
+            ..DroneStats::default()
+        };
+        assert_eq!(stats.control_data_ratio(), 1.5);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::config::DroneProfile;
+use crate::stats::DroneStats;
+use wg_2024::network::NodeId;
+use wg_2024::packet::NackType;
+
+/// Events emitted by a GetDroned drone in addition to the standard `DroneEvent`s.
+///
+/// The `DroneEvent` enum is shared by every drone of the network and cannot be extended,
+/// so the behaviors specific to GetDroned are reported on a dedicated, optional channel
+/// (see `GetDroned::set_telemetry_sender`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelemetryEvent {
+    /// A bit of the routing header of a forwarded packet was flipped on purpose.
+    HeaderCorrupted {
+        /// Session of the corrupted packet.
+        session_id: u64,
+        /// Index of the corrupted hop in the routing header.
+        hop_index: usize,
+        /// Hop before the corruption.
+        original: NodeId,
+        /// Hop after the corruption.
+        corrupted: NodeId,
+    },
+    /// The drone generated a flood response instead of forwarding the flood request.
+    FloodResponseGenerated {
+        /// Initiator of the flood.
+        initiator_id: NodeId,
+        /// ID of the flood.
+        flood_id: u64,
+    },
+    /// A fragment was refused because its session would exceed the maximum number of active sessions.
+    SessionRefused {
+        /// Session of the refused fragment.
+        session_id: u64,
+    },
+    /// A configuration profile was applied to the drone.
+    ProfileApplied(DroneProfile),
+    /// The last neighbor of the drone was removed: the drone is isolated.
+    Isolated,
+    /// The random number generator of the drone was reseeded with the given seed.
+    RngReseeded(u64),
+    /// A fragment was forwarded to a neighbor declared as a client or a server.
+    DeliveredToEndpoint {
+        /// Session of the fragment.
+        session_id: u64,
+        /// Index of the fragment in its session.
+        fragment_index: u64,
+        /// The endpoint the fragment was forwarded to.
+        endpoint: NodeId,
+    },
+    /// The stats of the drone were reset by a `GetDronedCommand::ResetStats`, with their values before the reset.
+    StatsReset(DroneStats),
+    /// A packet was sent to the controller with a `DroneEvent::ControllerShortcut`, which is
+    /// emitted right after this event.
+    ControllerShortcut {
+        /// Session of the packet.
+        session_id: u64,
+        /// Why the drone could not forward the packet itself.
+        reason: ShortcutReason,
+    },
+    /// The drone is about to send a packet to a neighbor (see `GetDroned::set_send_attempt_events`).
+    SendAttempted {
+        /// Session of the packet.
+        session_id: u64,
+        /// The neighbor the packet is sent to.
+        neighbor: NodeId,
+    },
+    /// The drone tried to send a packet to a neighbor, emitted right after `SendAttempted`.
+    SendCompleted {
+        /// Session of the packet.
+        session_id: u64,
+        /// The neighbor the packet was sent to.
+        neighbor: NodeId,
+        /// Whether the packet was enqueued on the channel of the neighbor.
+        success: bool,
+    },
+    /// A fragment was NACKed with `ErrorInRouting` because its next hop is its destination,
+    /// which is not a neighbor of the drone.
+    DestinationUnreachable {
+        /// Session of the fragment.
+        session_id: u64,
+        /// The destination of the fragment.
+        destination: NodeId,
+    },
+}
+
+/// Why a packet was sent to the controller instead of being forwarded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShortcutReason {
+    /// The routing header has no next hop.
+    NoNextHop,
+    /// The channel of the given next hop is disconnected (or full, with bounded channels).
+    SendFailed(NodeId),
+    /// The routing header of a control packet is invalid, for the reason given as a NACK type.
+    InvalidRoute(NackType),
+    /// The drone generated a flood response with no route back to the initiator.
+    UnroutableFloodResponse,
+}
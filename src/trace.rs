@@ -0,0 +1,15 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use wg_2024::network::NodeId;
+
+/// Paths traveled by the message fragments, keyed by `(session_id, fragment_index)`.
+///
+/// The `Packet` type is shared by every node of the network and has no room for a trace,
+/// so the trace is kept on the side: the controller creates one map, installs it on every drone
+/// with `GetDroned::set_path_trace_map`, and each drone appends its ID to the entry of every
+/// fragment it receives, whatever happens to the fragment next. The path of a fragment is then
+/// read in order from its entry, up to the drone that delivered, dropped or NACKed it.
+///
+/// Entries are never removed by the drones: the controller clears the map when it no longer
+/// needs the traces.
+pub type PathTraceMap = Arc<Mutex<HashMap<(u64, u64), Vec<NodeId>>>>;